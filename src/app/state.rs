@@ -2,13 +2,18 @@
 
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeSet, HashMap};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use InputResult::*;
 
 use crate::{
-    record::recorder::InputResultRecorder,
+    record::{input::InputResultRecord, recorder::InputResultRecorder},
     text_coord::TextCoord,
-    utils::{helpers::text_to_line_index, types::AsyncResult},
+    text_index::TextIndex,
+    utils::types::AsyncResult,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,35 +22,59 @@ pub enum InputResult {
     Wrong(char),
 }
 
+/// Where a player stands in the race
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PlayerStatus {
+    Finished,
+    Typing,
+    Forfeited,
+}
+
 #[derive(Debug, Clone)]
 pub struct PlayerState<'txt> {
     pub name: String,
     pub recorder: InputResultRecorder,
 
-    text: &'txt str,
+    index: Arc<TextIndex<'txt>>,
     pos: usize,
-    max_pos: usize,
 
     errors: BTreeSet<usize>,
+    forfeited: bool,
 }
 
 impl<'txt> PlayerState<'txt> {
-    pub fn new(name: String, text: &'txt str) -> Self {
+    pub fn new(name: String, index: Arc<TextIndex<'txt>>) -> Self {
         Self {
             name,
-            text,
-            pos: 0,
-            max_pos: text.chars().count(),
+            pos: index.next_typeable(0),
+            index,
             errors: BTreeSet::new(),
             recorder: InputResultRecorder::new(),
+            forfeited: false,
         }
     }
 
+    /// Bring the player back to the start of the text, with an idle
+    /// recorder and no inputs
+    pub fn reset(&mut self) {
+        *self = Self::new(self.name.clone(), self.index.clone());
+    }
+
     pub fn process_input(&mut self, input_ch: char) -> Option<InputResult> {
-        let cursor_ch = self.text.chars().nth(self.pos)?;
+        let elapsed = self.recorder.elapsed();
+        self.process_input_at(input_ch, elapsed)
+    }
+
+    /// Process an input timestamped by the clock of whoever typed it
+    pub fn process_input_at(
+        &mut self,
+        input_ch: char,
+        elapsed: Duration,
+    ) -> Option<InputResult> {
+        let cursor_ch = self.index.expected(self.pos)?;
 
         let input_result = if input_ch == cursor_ch {
-            self.pos += 1;
+            self.pos = self.index.next_typeable(self.pos + 1);
 
             // TODO: take whitespace autoskip into account for input recording
 
@@ -58,7 +87,7 @@ impl<'txt> PlayerState<'txt> {
             Wrong(cursor_ch)
         };
 
-        self.recorder.push(input_result);
+        self.recorder.push_at(elapsed, input_result);
         Some(input_result)
     }
 
@@ -73,29 +102,50 @@ impl<'txt> PlayerState<'txt> {
     // }
 
     pub fn get_error_coords(&self) -> Vec<TextCoord> {
-        let text_lines = self.text.split_inclusive('\n').collect::<Vec<_>>();
-        let errors: Vec<usize> = Vec::from_iter(self.errors.clone());
-        let coords = text_to_line_index(errors, &text_lines).unwrap();
-
-        coords.into_iter().map_into::<TextCoord>().collect()
+        self.errors
+            .iter()
+            .filter_map(|&pos| self.index.coord(pos))
+            .collect()
     }
 
     pub fn get_cursor_coord(&self) -> Option<TextCoord> {
-        let text_lines = self.text.split_inclusive('\n').collect::<Vec<_>>();
-        let coords_lst = text_to_line_index([self.pos], &text_lines).ok()?;
-
-        Some(coords_lst[0].into())
+        self.index.coord(self.pos)
     }
 
     pub fn is_done(&self) -> bool {
-        self.pos == self.max_pos
+        self.pos == self.index.len()
+    }
+
+    /// Stop the player for the rest of the session, its cursor
+    /// is no longer displayed
+    pub fn forfeit(&mut self) {
+        self.forfeited = true;
+    }
+
+    pub fn status(&self) -> PlayerStatus {
+        if self.forfeited {
+            PlayerStatus::Forfeited
+        } else if self.is_done() {
+            PlayerStatus::Finished
+        } else {
+            PlayerStatus::Typing
+        }
+    }
+
+    /// Share of the text behind the cursor, between 0 and 1
+    pub fn progress(&self) -> f64 {
+        if self.index.is_empty() {
+            1.0
+        } else {
+            self.pos as f64 / self.index.len() as f64
+        }
     }
 
     pub fn set_cursor(&mut self, pos: usize) -> Result<(), &'static str> {
-        if pos > self.text.chars().count() {
+        if pos > self.index.len() {
             Err("cursor out of bounds")
         } else {
-            self.pos = pos;
+            self.pos = self.index.next_typeable(pos);
             Ok(())
         }
     }
@@ -108,6 +158,17 @@ impl<'txt> PlayerState<'txt> {
         self.pos
     }
 
+    /// Time since the last input, or since the start of the race
+    pub fn idle_time(&self) -> Duration {
+        let last_input = self
+            .recorder
+            .record()
+            .inputs
+            .last()
+            .map_or(Duration::ZERO, |(elapsed, _)| elapsed.duration);
+        self.recorder.elapsed().saturating_sub(last_input)
+    }
+
     pub fn last_input(&self) -> Option<InputResult> {
         self.recorder
             .record()
@@ -116,8 +177,12 @@ impl<'txt> PlayerState<'txt> {
             .map(|(_, input)| *input)
     }
 
-    pub fn text(&self) -> &str {
-        self.text
+    pub fn text(&self) -> &'txt str {
+        self.index.text()
+    }
+
+    pub fn index(&self) -> &Arc<TextIndex<'txt>> {
+        &self.index
     }
 
     pub fn name(&self) -> &String {
@@ -127,23 +192,23 @@ impl<'txt> PlayerState<'txt> {
 
 #[derive(Debug, Clone)]
 pub struct PlayerPool<'txt> {
-    text: &'txt str,
+    index: Arc<TextIndex<'txt>>,
     players: HashMap<String, PlayerState<'txt>>,
 }
 
 impl<'txt> PlayerPool<'txt> {
-    pub fn new(text: &'txt str) -> Self {
+    pub fn new(index: Arc<TextIndex<'txt>>) -> Self {
         let players: HashMap<String, PlayerState<'txt>> = Default::default();
 
-        Self { text, players }
+        Self { index, players }
     }
 
     pub fn with_players(mut self, usernames: &[&str]) -> Self {
         for &user in usernames {
             let username = user.to_string();
-            self.players
-                .entry(username.clone())
-                .or_insert_with(|| PlayerState::new(username, self.text));
+            self.players.entry(username.clone()).or_insert_with(|| {
+                PlayerState::new(username, self.index.clone())
+            });
         }
 
         self
@@ -166,6 +231,26 @@ impl<'txt> PlayerPool<'txt> {
         Ok(input_result)
     }
 
+    /// Process an input of a remote player along with the time
+    /// at which it was typed on their side
+    pub fn process_input_at(
+        &mut self,
+        username: &str,
+        input_ch: char,
+        elapsed: Duration,
+    ) -> AsyncResult<InputResult> {
+        let player = self
+            .players
+            .get_mut(username)
+            .ok_or("Player does not exist")?;
+
+        let input_result = player
+            .process_input_at(input_ch, elapsed)
+            .ok_or("Played already reached the end")?;
+
+        Ok(input_result)
+    }
+
     pub fn advance_player(&mut self, username: &str) -> AsyncResult<()> {
         let player = self
             .players
@@ -177,7 +262,84 @@ impl<'txt> PlayerPool<'txt> {
         Ok(())
     }
 
-    pub fn player(&self, username: &str) -> Option<&PlayerState> {
+    /// Apply an input replayed from a record, the cursor only
+    /// moves on correct inputs
+    pub fn replay_input(
+        &mut self,
+        username: &str,
+        input: InputResult,
+    ) -> AsyncResult<()> {
+        let player = self
+            .players
+            .get_mut(username)
+            .ok_or("Player does not exist")?;
+
+        if let Correct = input {
+            player.advance_cursor()?;
+        }
+        player.recorder.push(input);
+
+        Ok(())
+    }
+
+    /// Bring a player to the state described by its inputs so far,
+    /// keeping their original timestamps
+    pub fn restore(
+        &mut self,
+        username: &str,
+        record: &InputResultRecord,
+        forfeited: bool,
+    ) -> AsyncResult<()> {
+        let player = self
+            .players
+            .get_mut(username)
+            .ok_or("Player does not exist")?;
+
+        let mut restored =
+            PlayerState::new(username.into(), self.index.clone());
+        restored.recorder = player.recorder.clone();
+        restored.recorder.clear();
+        for (elapsed, input) in &record.inputs {
+            match input {
+                Correct => restored.advance_cursor()?,
+                Wrong(_) => {
+                    restored.errors.insert(restored.pos);
+                }
+            }
+            restored.recorder.push_at(elapsed.duration, *input);
+        }
+        restored.forfeited = forfeited;
+        *player = restored;
+
+        Ok(())
+    }
+
+    /// Player furthest in the text among those still typing, or
+    /// among all players once the race is over
+    pub fn leader(&self) -> Option<&PlayerState<'txt>> {
+        let ahead = |a: &&PlayerState, b: &&PlayerState| {
+            a.cursor()
+                .cmp(&b.cursor())
+                .then_with(|| b.name().cmp(a.name()))
+        };
+        self.players
+            .values()
+            .filter(|p| p.status() == PlayerStatus::Typing)
+            .max_by(ahead)
+            .or_else(|| self.players.values().max_by(ahead))
+    }
+
+    /// Keep a player that left the session, marked as forfeited
+    pub fn forfeit(&mut self, username: &str) -> AsyncResult<()> {
+        self.players
+            .get_mut(username)
+            .ok_or("Player does not exist")?
+            .forfeit();
+
+        Ok(())
+    }
+
+    pub fn player(&self, username: &str) -> Option<&PlayerState<'txt>> {
         self.players.get(username)
     }
 
@@ -185,45 +347,197 @@ impl<'txt> PlayerPool<'txt> {
         &self.players
     }
 
-    pub fn remove(&mut self, username: &str) -> Option<PlayerState> {
+    pub fn remove(&mut self, username: &str) -> Option<PlayerState<'txt>> {
         self.players.remove(username)
     }
 
     pub fn text(&self) -> &'txt str {
-        self.text
+        self.index.text()
+    }
+
+    /// Bring every player back to the start of the text
+    pub fn reset(&mut self) {
+        for player in self.players.values_mut() {
+            player.reset();
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.start_at(Instant::now());
     }
 
+    pub fn start_at(&mut self, origin: Instant) {
+        for player in self.players.values_mut() {
+            player.recorder.start_at(origin);
+        }
+    }
+
+    pub fn pause(&mut self) {
+        for player in self.players.values_mut() {
+            player.recorder.pause();
+        }
+    }
+
+    pub fn resume(&mut self) {
+        for player in self.players.values_mut() {
+            player.recorder.resume();
+        }
+    }
+
+    /// Whether every player either finished or forfeited
     pub fn are_done(&self) -> bool {
         self.players
-            .iter()
-            .map(|(_, state)| state.is_done())
-            .all(|done| done)
+            .values()
+            .all(|state| state.status() != PlayerStatus::Typing)
     }
 
-    pub fn get_cursor_coords(&self) -> HashMap<TextCoord, Option<InputResult>> {
-        let text_lines = self.text.split_inclusive('\n').collect::<Vec<_>>();
+    /// Cursor coordinates of the players still typing, along with
+    /// the names of the players sharing each coordinate, sorted
+    pub fn get_cursor_coords(&self) -> HashMap<TextCoord, Vec<&str>> {
+        let mut players = self.players.values().collect::<Vec<_>>();
+        players.sort_by_key(|pstate| pstate.name());
+
+        let mut coords: HashMap<TextCoord, Vec<&str>> = HashMap::new();
+        for pstate in players {
+            if pstate.status() == PlayerStatus::Forfeited {
+                continue;
+            }
+            if let Some(coord) = self.index.coord(pstate.cursor()) {
+                coords.entry(coord).or_default().push(pstate.name());
+            }
+        }
 
-        let mut player_tuples = self
-            .players()
-            .iter()
-            .filter_map(|(_, pstate)| {
-                if pstate.cursor() < self.text.len() {
-                    Some((pstate.cursor(), pstate.last_input()))
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
+        coords
+    }
 
-        player_tuples.sort_by(|(ca, _), (cb, _)| ca.cmp(cb));
-        let (indexes, inputs): (Vec<usize>, Vec<Option<InputResult>>) =
-            player_tuples.into_iter().unzip();
-        let coords = text_to_line_index(indexes, &text_lines).unwrap();
+    /// Stable index of each player among the pool, sorted by name
+    pub fn player_indexes(&self) -> HashMap<&str, usize> {
+        let mut names = self.players.keys().map(String::as_str).collect_vec();
+        names.sort_unstable();
 
-        coords
+        names
             .into_iter()
-            .map(Into::<TextCoord>::into)
-            .zip(inputs)
-            .collect::<HashMap<_, _>>()
+            .enumerate()
+            .map(|(i, name)| (name, i))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn typing_multilingual_text() {
+        let text = "nai\u{308}ve\r\n日本 👍🏽";
+        let mut player =
+            PlayerState::new("p".into(), Arc::new(TextIndex::new(text)));
+
+        for c in "na".chars() {
+            assert_eq!(player.process_input(c), Some(Correct));
+        }
+        assert_eq!(player.process_input('i'), Some(Wrong('ï')));
+        for c in "ïve\n日本 ".chars() {
+            assert_eq!(player.process_input(c), Some(Correct));
+        }
+
+        assert_eq!(player.get_error_coords(), [TextCoord::new(0, 2)]);
+        assert_eq!(player.get_cursor_coord(), Some(TextCoord::new(1, 3)));
+        assert_eq!(player.process_input('👍'), Some(Correct));
+        assert!(player.is_done());
+    }
+
+    #[test]
+    fn skipped_graphemes() {
+        let text = "\u{2502}a\u{2502}\u{2502}b";
+        let index = TextIndex::with_skips(text, |_, g| g == "\u{2502}");
+        let mut player = PlayerState::new("p".into(), Arc::new(index));
+
+        assert_eq!(player.cursor(), 1);
+        assert_eq!(player.process_input('a'), Some(Correct));
+        assert_eq!(player.cursor(), 4);
+        player.advance_cursor().unwrap();
+        assert!(player.is_done());
+    }
+
+    #[test]
+    fn stacked_cursors() {
+        let index = Arc::new(TextIndex::new("ab\ncd"));
+        let mut pool =
+            PlayerPool::new(index).with_players(&["zoe", "al", "bo"]);
+        pool.process_input("zoe", 'a').unwrap();
+        pool.forfeit("bo").unwrap();
+
+        let coords = pool.get_cursor_coords();
+        assert_eq!(coords.len(), 2);
+        assert_eq!(coords[&TextCoord::new(0, 0)], ["al"]);
+        assert_eq!(coords[&TextCoord::new(0, 1)], ["zoe"]);
+
+        pool.process_input("al", 'a').unwrap();
+        let coords = pool.get_cursor_coords();
+        assert_eq!(coords[&TextCoord::new(0, 1)], ["al", "zoe"]);
+    }
+
+    #[test]
+    fn idle_time() {
+        let index = Arc::new(TextIndex::new("abc"));
+        let mut player = PlayerState::new("p".into(), index);
+        player
+            .recorder
+            .start_at(Instant::now() - Duration::from_secs(10));
+        assert!(player.idle_time() >= Duration::from_secs(10));
+
+        player.process_input_at('a', Duration::from_secs(8));
+        let idle = player.idle_time();
+        assert!(idle >= Duration::from_secs(2));
+        assert!(idle < Duration::from_secs(8));
+    }
+
+    #[test]
+    fn restart_mid_race() {
+        let index = Arc::new(TextIndex::new("abc"));
+        let mut pool = PlayerPool::new(index.clone()).with_players(&["al"]);
+        let mut main = PlayerState::new("me".into(), index);
+        main.recorder.start();
+        pool.start();
+        main.process_input('a').unwrap();
+        main.process_input('x').unwrap();
+        pool.process_input("al", 'a').unwrap();
+
+        main.reset();
+        pool.reset();
+        let al = pool.player("al").unwrap();
+        for player in [&main, al] {
+            assert_eq!(player.cursor(), 0);
+            assert!(player.get_error_coords().is_empty());
+            assert!(player.recorder.record().inputs.is_empty());
+            assert!(!player.recorder.is_started());
+        }
+        assert_eq!(main.name(), "me");
+    }
+
+    #[test]
+    fn restored_players() {
+        let index = Arc::new(TextIndex::new("abc"));
+        let mut pool = PlayerPool::new(index).with_players(&["al", "bo"]);
+        let record = InputResultRecord {
+            inputs: vec![
+                (Duration::from_millis(100).into(), Correct),
+                (Duration::from_millis(200).into(), Wrong('b')),
+                (Duration::from_millis(300).into(), Correct),
+            ],
+        };
+
+        pool.process_input("bo", 'a').unwrap();
+        pool.restore("al", &record, false).unwrap();
+        let al = pool.player("al").unwrap();
+        assert_eq!(al.cursor(), 2);
+        assert_eq!(al.get_error_coords(), [TextCoord::new(0, 1)]);
+        assert_eq!(al.recorder.record(), &record);
+        assert_eq!(pool.leader().unwrap().name(), "al");
+
+        pool.restore("al", &record, true).unwrap();
+        assert_eq!(pool.leader().unwrap().name(), "bo");
     }
 }
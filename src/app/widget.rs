@@ -1,40 +1,237 @@
 use crate::{
+    config::color,
+    line::stylizer::LineStylizer,
     text_coord::TextCoord,
-    text_view::{Anchor, TextView},
-    utils::types::StyledLine,
+    text_view::{Anchor, Minimap, TextView, TextViewState},
+    utils::{color::ColorSupport, types::StyledLine},
 };
+use serde::Deserialize;
 use std::collections::HashMap;
-use tui::style::Style;
+use syntect::highlighting::Theme;
+use tui::style::{Modifier, Style};
 use tui::text::StyledGrapheme;
-use tui::widgets::Block;
+use tui::widgets::{Block, StatefulWidget};
 use tui::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::app::InputResult;
 
 use super::state::{PlayerPool, PlayerState};
 
+/// Colors of the cursors and mistakes drawn over the highlighted text
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Palette {
+    #[serde(deserialize_with = "color::deserialize")]
+    pub cursor: Color,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub cursor_text: Color,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub wrong: Color,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub wrong_text: Color,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub mistake: Color,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub mistake_text: Color,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub opponent: Color,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub opponent_text: Color,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub ghost: Color,
+
+    /// Colors cycled through for each opponent, `opponent` is
+    /// used for all of them when empty
+    #[serde(deserialize_with = "color::deserialize_vec")]
+    pub opponents: Vec<Color>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            cursor: Color::White,
+            cursor_text: Color::Black,
+            wrong: Color::Red,
+            wrong_text: Color::White,
+            mistake: Color::Yellow,
+            mistake_text: Color::Black,
+            opponent: Color::Rgb(20, 20, 20),
+            opponent_text: Color::White,
+            ghost: Color::Rgb(20, 20, 20),
+            opponents: vec![
+                Color::Rgb(140, 50, 50),
+                Color::Rgb(50, 110, 50),
+                Color::Rgb(50, 70, 140),
+                Color::Rgb(130, 100, 30),
+                Color::Rgb(110, 50, 120),
+                Color::Rgb(40, 110, 120),
+            ],
+        }
+    }
+}
+
+impl Palette {
+    /// Palette derived from the caret, background and highlight
+    /// colors of a syntect theme, defaults fill the gaps
+    pub fn from_theme(theme: &Theme) -> Self {
+        let rgb = |c: syntect::highlighting::Color| Color::Rgb(c.r, c.g, c.b);
+        let settings = &theme.settings;
+        let default = Self::default();
+
+        let background = settings.background.map(rgb);
+        let foreground = settings.foreground.map(rgb);
+
+        Self {
+            cursor: settings
+                .caret
+                .map(rgb)
+                .or(foreground)
+                .unwrap_or(default.cursor),
+            cursor_text: background.unwrap_or(default.cursor_text),
+            mistake_text: background.unwrap_or(default.mistake_text),
+            opponent: settings
+                .line_highlight
+                .or(settings.selection)
+                .map(rgb)
+                .unwrap_or(default.opponent),
+            opponent_text: foreground.unwrap_or(default.opponent_text),
+            ..default
+        }
+    }
+
+    fn opponent(&self, index: usize) -> Color {
+        if self.opponents.is_empty() {
+            self.opponent
+        } else {
+            self.opponents[index % self.opponents.len()]
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorShape {
+    /// Colored cell behind the character
+    Block,
+    /// Underlined character in the cursor color
+    Underline,
+    /// Bold character in the cursor color, without background
+    Bar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorStyle {
+    Background,
+    Underline,
+    Strike,
+}
+
+/// Tag drawn next to the cursors of the other players
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelStyle {
+    None,
+    Initial,
+    Name,
+}
+
+/// How the text follows the main cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollMode {
+    /// Current line kept in the middle of the view
+    Center,
+    /// Current line kept on a fixed row
+    Typewriter,
+    /// View flipped a full screen at a time
+    Page,
+}
+
+/// How the cursors, mistakes and typed text are drawn
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DacttyloStyle {
+    pub cursor: CursorShape,
+    pub errors: ErrorStyle,
+
+    /// Dim the text the player has already typed
+    pub dim_typed: bool,
+
+    pub scroll: ScrollMode,
+
+    /// Row of the current line in typewriter mode
+    pub typewriter_row: u16,
+
+    /// Dim every line but the current one
+    pub focus: bool,
+
+    /// Derive the palette from the highlighting theme instead
+    /// of the configured colors
+    pub match_theme: bool,
+
+    /// Show line numbers on the left of the text
+    pub line_numbers: bool,
+
+    /// Show the progress, mistakes and opponents over the whole
+    /// text on the right
+    pub minimap: bool,
+
+    pub labels: LabelStyle,
+}
+
+impl Default for DacttyloStyle {
+    fn default() -> Self {
+        Self {
+            cursor: CursorShape::Block,
+            errors: ErrorStyle::Background,
+            dim_typed: false,
+            scroll: ScrollMode::Center,
+            typewriter_row: 3,
+            focus: false,
+            match_theme: false,
+            line_numbers: false,
+            minimap: false,
+            labels: LabelStyle::None,
+        }
+    }
+}
+
 pub struct DacttyloWidget<'txt, 'ln> {
     block: Block<'txt>,
 
-    main: &'txt PlayerState<'txt>,
-    opponents: &'txt PlayerPool<'txt>,
+    main: &'ln PlayerState<'txt>,
+    opponents: &'ln PlayerPool<'txt>,
+    ghost: Option<&'ln str>,
+    spectating: bool,
 
     highlighted_content: &'ln [StyledLine<'txt>],
     bg_color: Color,
+    palette: Palette,
+    style: DacttyloStyle,
+    tab_width: usize,
+    monochrome: bool,
 }
 
 impl<'txt, 'ln> DacttyloWidget<'txt, 'ln> {
     pub fn new(
-        main: &'txt PlayerState,
-        opponents: &'txt PlayerPool,
+        main: &'ln PlayerState<'txt>,
+        opponents: &'ln PlayerPool<'txt>,
         lines: &'ln [StyledLine<'txt>],
     ) -> Self {
         Self {
             main,
             opponents,
+            ghost: None,
+            spectating: false,
             highlighted_content: lines,
             block: Default::default(),
             bg_color: Color::Reset,
+            palette: Default::default(),
+            style: Default::default(),
+            tab_width: 4,
+            monochrome: false,
         }
     }
 
@@ -48,11 +245,71 @@ impl<'txt, 'ln> DacttyloWidget<'txt, 'ln> {
         self
     }
 
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    pub fn style(mut self, style: DacttyloStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Name of the opponent replaying a record, drawn apart
+    /// from the other players
+    pub fn ghost(mut self, name: &'ln str) -> Self {
+        self.ghost = Some(name);
+        self
+    }
+
+    /// Draw the opponents only, the view follows the leading one
+    pub fn spectate(mut self) -> Self {
+        self.spectating = true;
+        self
+    }
+
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Without color support, cursors and mistakes are drawn
+    /// with text modifiers instead of colors
+    pub fn color_support(mut self, support: ColorSupport) -> Self {
+        self.monochrome = support == ColorSupport::NoColor;
+        self
+    }
+
+    /// Modifier-only replacement for `style` when drawing without colors
+    fn mono(&self, style: Style, modifier: Modifier) -> Style {
+        if self.monochrome {
+            Style::default().add_modifier(modifier)
+        } else {
+            style
+        }
+    }
+
+    fn cursor_style(&self, color: Color, text: Color) -> Style {
+        let style = Style::default();
+        match self.style.cursor {
+            CursorShape::Block => style.bg(color).fg(text),
+            CursorShape::Underline => {
+                style.fg(color).add_modifier(Modifier::UNDERLINED)
+            }
+            CursorShape::Bar => style.fg(color).add_modifier(Modifier::BOLD),
+        }
+    }
+
     fn get_main_style(&self) -> Option<(TextCoord, Style)> {
         if let Some(player_coords) = self.main.get_cursor_coord() {
-            let style = Style::default();
-            let neutral = style.bg(Color::White).fg(Color::Black);
-            let wrong = style.bg(Color::Red).fg(Color::White);
+            let palette = &self.palette;
+            let neutral =
+                self.cursor_style(palette.cursor, palette.cursor_text);
+            let wrong = self.cursor_style(palette.wrong, palette.wrong_text);
+
+            let neutral = self.mono(neutral, Modifier::REVERSED);
+            let wrong =
+                self.mono(wrong, Modifier::REVERSED | Modifier::UNDERLINED);
 
             let style = match self.main.last_input() {
                 Some(InputResult::Wrong(_)) => wrong,
@@ -68,58 +325,209 @@ impl<'txt, 'ln> DacttyloWidget<'txt, 'ln> {
     fn get_main_error_styles(&self) -> HashMap<TextCoord, Style> {
         let coords = self.main.get_error_coords();
 
+        let palette = &self.palette;
         let style = Style::default();
-        let yellow = style.bg(Color::Yellow).fg(Color::Black);
+        let style = match self.style.errors {
+            ErrorStyle::Background => {
+                style.bg(palette.mistake).fg(palette.mistake_text)
+            }
+            ErrorStyle::Underline => {
+                style.fg(palette.mistake).add_modifier(Modifier::UNDERLINED)
+            }
+            ErrorStyle::Strike => style
+                .fg(palette.mistake)
+                .add_modifier(Modifier::CROSSED_OUT),
+        };
+        let style = self.mono(style, Modifier::UNDERLINED);
 
-        coords.into_iter().map(|coord| (coord, yellow)).collect()
+        coords.into_iter().map(|coord| (coord, style)).collect()
     }
 
-    fn get_opponent_styles(&self) -> HashMap<TextCoord, Style> {
-        let opponent_coords = self.opponents.get_cursor_coords();
+    fn get_minimap(&self, current_ln: usize) -> Minimap {
+        let palette = &self.palette;
+        let track = Style::default().fg(Color::DarkGray);
+        let progress = Style::default().fg(palette.cursor);
+        let mut minimap = Minimap::new()
+            .track(self.mono(track, Modifier::DIM))
+            .progress(current_ln, self.mono(progress, Modifier::BOLD));
 
-        let style = Style::default();
-        let grey = style.bg(Color::Rgb(20, 20, 20)).fg(Color::White);
+        let mistake = Style::default().fg(palette.mistake);
+        let mistake = self.mono(mistake, Modifier::UNDERLINED);
+        for coord in self.main.get_error_coords() {
+            minimap = minimap.mark(coord.ln, mistake);
+        }
+
+        let indexes = self.opponents.player_indexes();
+        for (coord, names) in self.opponents.get_cursor_coords() {
+            for name in names {
+                let style = self.opponent_style(name, &indexes);
+                let color = style.bg.unwrap_or(palette.opponent);
+                let style = Style::default().fg(color);
+                let style = self.mono(style, Modifier::DIM);
+                minimap = minimap.mark(coord.ln, style);
+            }
+        }
+
+        minimap
+    }
+
+    /// Stable style of an opponent, the ghost gets its own
+    fn opponent_style(
+        &self,
+        name: &str,
+        indexes: &HashMap<&str, usize>,
+    ) -> Style {
+        let palette = &self.palette;
+        let style = Style::default().fg(palette.opponent_text);
+
+        if self.ghost == Some(name) {
+            let style = style.bg(palette.ghost).add_modifier(Modifier::ITALIC);
+            self.mono(style, Modifier::DIM | Modifier::UNDERLINED)
+        } else {
+            let index = indexes.get(name).copied().unwrap_or(0);
+            let style = style.bg(palette.opponent(index));
+            self.mono(style, Modifier::DIM | Modifier::REVERSED)
+        }
+    }
 
-        opponent_coords
+    /// Opponent cursors, a cell shared by several opponents takes the
+    /// style of the first one and is underlined when no label tells
+    /// them apart
+    fn get_opponent_styles(&self) -> HashMap<TextCoord, Style> {
+        let indexes = self.opponents.player_indexes();
+
+        self.opponents
+            .get_cursor_coords()
             .into_iter()
-            .map(|(coord, _)| (coord, grey))
+            .map(|(coord, names)| {
+                let mut style = self.opponent_style(names[0], &indexes);
+                if names.len() > 1 && self.style.labels == LabelStyle::None {
+                    style = style.add_modifier(Modifier::UNDERLINED);
+                }
+                (coord, style)
+            })
             .collect()
     }
 
-    fn apply_cursors(
-        styles: HashMap<TextCoord, Style>,
-        mut hl_lines: Vec<Vec<StyledGrapheme>>,
-    ) -> Vec<Vec<StyledGrapheme>> {
-        for (coord, style) in styles {
-            hl_lines[coord.ln][coord.x].style = style;
-        }
+    /// Tags of the players at each opponent cursor, one per player
+    fn get_opponent_labels(
+        &self,
+    ) -> Vec<(TextCoord, Vec<StyledGrapheme<'ln>>)> {
+        let opponents: &'ln PlayerPool<'txt> = self.opponents;
+        let indexes = opponents.player_indexes();
 
-        hl_lines
+        opponents
+            .get_cursor_coords()
+            .into_iter()
+            .map(|(coord, names)| {
+                let graphemes = names
+                    .into_iter()
+                    .flat_map(|name| {
+                        let style = self
+                            .opponent_style(name, &indexes)
+                            .add_modifier(Modifier::BOLD);
+                        let symbols: Vec<&str> = match self.style.labels {
+                            LabelStyle::None => vec![],
+                            LabelStyle::Initial => {
+                                name.graphemes(true).take(1).collect()
+                            }
+                            LabelStyle::Name => name.graphemes(true).collect(),
+                        };
+
+                        symbols
+                            .into_iter()
+                            .map(move |symbol| StyledGrapheme { symbol, style })
+                    })
+                    .collect();
+                (coord, graphemes)
+            })
+            .collect()
     }
 }
 
-impl<'txt, 'ln> Widget for DacttyloWidget<'txt, 'ln> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let mut styles = self.get_opponent_styles();
-        let error_styles = self.get_main_error_styles();
-        styles.extend(error_styles);
+impl<'txt, 'ln> StatefulWidget for DacttyloWidget<'txt, 'ln> {
+    type State = TextViewState<'txt>;
 
-        let main_style = self.get_main_style();
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let mut styles = self.get_opponent_styles();
+        let mut main_style = None;
+        if !self.spectating {
+            styles.extend(self.get_main_error_styles());
+            main_style = self.get_main_style();
+        }
         if let Some((coord, style)) = &main_style {
             styles.insert(coord.clone(), *style);
         }
 
-        let styled_lines =
-            Self::apply_cursors(styles, self.highlighted_content.to_owned());
+        // spectators follow the leading player
+        let followed = if self.spectating {
+            self.opponents.leader().unwrap_or(self.main)
+        } else {
+            self.main
+        };
+        let line_count = self.highlighted_content.len();
+        let current_ln = followed
+            .get_cursor_coord()
+            .map(|coord| coord.ln)
+            .unwrap_or_else(|| line_count.saturating_sub(1));
 
-        let current_ln = main_style
-            .map(|(coord, _)| coord.ln)
-            .unwrap_or(styled_lines.len() - 1);
+        let dim = Style::default().add_modifier(Modifier::DIM);
+        let mut view = TextView::from_styled_content(self.highlighted_content);
 
-        TextView::from_styled_content(&styled_lines)
+        if self.style.dim_typed && !self.spectating {
+            match &main_style {
+                Some((cursor, _)) => {
+                    view = view.line_patch(0..cursor.ln, dim);
+                    for x in 0..cursor.x {
+                        let coord = TextCoord::new(cursor.ln, x);
+                        let style = styles.entry(coord).or_default();
+                        *style = dim.patch(*style);
+                    }
+                }
+                None => view = view.line_patch(0..line_count, dim),
+            }
+        }
+        if self.style.focus {
+            view = view
+                .line_patch(0..current_ln, dim)
+                .line_patch(current_ln + 1..line_count, dim);
+        }
+
+        let anchor = match self.style.scroll {
+            ScrollMode::Center => Anchor::Center(current_ln),
+            ScrollMode::Typewriter => {
+                Anchor::Row(current_ln, self.style.typewriter_row)
+            }
+            ScrollMode::Page => Anchor::Page(current_ln),
+        };
+
+        if self.style.line_numbers {
+            let gutter = Style::default().fg(Color::DarkGray);
+            view = view.gutter(self.mono(gutter, Modifier::DIM));
+        }
+        if self.style.minimap {
+            view = view.minimap(self.get_minimap(current_ln));
+        }
+
+        if self.style.labels != LabelStyle::None {
+            for (coord, label) in self.get_opponent_labels() {
+                view = view.inlay(coord, label);
+            }
+        }
+
+        let view = view
+            .overlays(styles)
             .block(self.block)
-            .anchor(Anchor::Center(current_ln))
-            .bg_color(self.bg_color)
-            .render(area, buf);
+            .anchor(anchor)
+            .line_processor(Box::new(LineStylizer::new(self.tab_width)))
+            .bg_color(self.bg_color);
+
+        StatefulWidget::render(view, area, buf, state);
+    }
+}
+
+impl<'txt, 'ln> Widget for DacttyloWidget<'txt, 'ln> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        StatefulWidget::render(self, area, buf, &mut TextViewState::new());
     }
 }
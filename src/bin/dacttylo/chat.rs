@@ -0,0 +1,214 @@
+use crate::protocol::{ChatMessage, DacttyloCommand, Emote, EmoteMessage};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use dacttylo::{
+    session::SessionClient,
+    utils::{tui::input_width, types::AsyncResult},
+};
+use std::io::Stdout;
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Emotes sent with `F1` onwards
+const EMOTES: [Emote; 4] = [Emote::Gg, Emote::Fire, Emote::Clap, Emote::Sweat];
+
+/// Line of the chat log
+enum LogLine {
+    Chat(ChatMessage),
+    Notice(String),
+}
+
+/// Messages exchanged from the lobby to the report, along with the
+/// message being typed
+#[derive(Default)]
+pub struct Chat {
+    log: Vec<LogLine>,
+    input: String,
+}
+
+impl Chat {
+    pub fn push(&mut self, msg: ChatMessage) {
+        self.log.push(LogLine::Chat(msg));
+    }
+
+    /// Log the messages and emotes pushed by the other peers, other
+    /// commands are left to the caller
+    pub fn receive(&mut self, cmd: DacttyloCommand) {
+        match cmd {
+            DacttyloCommand::Chat(msg) => self.push(msg),
+            DacttyloCommand::Emote(msg) => self.push(msg.into()),
+            _ => (),
+        }
+    }
+
+    /// Event of the session, shown apart from the messages
+    pub fn notice(&mut self, notice: String) {
+        self.log.push(LogLine::Notice(notice));
+    }
+
+    /// Edit the message being typed, returns it when sent with enter
+    pub fn handle_key(&mut self, key: &KeyEvent) -> Option<String> {
+        match key.code {
+            KeyCode::Enter if !self.input.trim().is_empty() => {
+                let text = self.input.trim().to_owned();
+                self.input.clear();
+                Some(text)
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                None
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub async fn send(
+        &mut self,
+        client: &mut SessionClient,
+        from: &str,
+        text: String,
+    ) -> AsyncResult<()> {
+        let from = from.to_owned();
+        let msg = ChatMessage { from, text };
+        client.push(&DacttyloCommand::Chat(msg.clone())).await?;
+        self.push(msg);
+        Ok(())
+    }
+
+    pub async fn send_emote(
+        &mut self,
+        client: &mut SessionClient,
+        from: &str,
+        emote: Emote,
+    ) -> AsyncResult<()> {
+        let from = from.to_owned();
+        let msg = EmoteMessage { from, emote };
+        client.push(&DacttyloCommand::Emote(msg.clone())).await?;
+        self.push(msg.into());
+        Ok(())
+    }
+}
+
+/// Emote sent by a function key, if any
+pub fn emote_for(key: &KeyEvent) -> Option<Emote> {
+    match key.code {
+        KeyCode::F(n) if key.modifiers == KeyModifiers::NONE => {
+            EMOTES.get(usize::from(n).checked_sub(1)?).copied()
+        }
+        _ => None,
+    }
+}
+
+/// Function keys along with the emote they send
+pub fn emote_help() -> String {
+    EMOTES
+        .iter()
+        .enumerate()
+        .map(|(i, emote)| format!("F{} {}", i + 1, emote))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// Latest messages of the chat that fit the area
+pub fn render_log(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    chat: &Chat,
+    title: &str,
+) {
+    // the oldest lines go first when running out of room
+    let height = area.height.saturating_sub(2) as usize;
+    let skip = chat.log.len().saturating_sub(height);
+    let lines: Vec<Spans> = chat.log[skip..]
+        .iter()
+        .map(|line| match line {
+            LogLine::Chat(msg) => Spans::from(vec![
+                Span::styled(
+                    format!("{}: ", msg.from),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(msg.text.as_str()),
+            ]),
+            LogLine::Notice(notice) => Spans::from(Span::styled(
+                notice.as_str(),
+                Style::default()
+                    .fg(Color::Gray)
+                    .add_modifier(Modifier::ITALIC),
+            )),
+        })
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .style(Style::default().bg(Color::Reset).fg(Color::White));
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Message being typed, with the cursor at its end
+pub fn render_input(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    chat: &Chat,
+) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Message")
+        .style(Style::default().bg(Color::Reset).fg(Color::White));
+    f.render_widget(Paragraph::new(chat.input.as_str()).block(block), area);
+
+    let x = area.x + 1 + input_width(&chat.input) as u16;
+    f.set_cursor(x.min(area.right().saturating_sub(2)), area.y + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emote_keys() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        let none = KeyModifiers::NONE;
+        assert_eq!(emote_for(&key(KeyCode::F(1), none)), Some(Emote::Gg));
+        assert_eq!(emote_for(&key(KeyCode::F(4), none)), Some(Emote::Sweat));
+        assert_eq!(emote_for(&key(KeyCode::F(5), none)), None);
+        assert_eq!(emote_for(&key(KeyCode::F(0), none)), None);
+        assert_eq!(emote_for(&key(KeyCode::Char('1'), none)), None);
+        let shift = KeyModifiers::SHIFT;
+        assert_eq!(emote_for(&key(KeyCode::F(1), shift)), None);
+        assert_eq!(emote_help(), "F1 GG  F2 🔥  F3 👏  F4 😅");
+    }
+
+    #[test]
+    fn received_messages() {
+        let mut chat = Chat::default();
+        let from = "al".to_owned();
+        let text = "hi".to_owned();
+        chat.receive(DacttyloCommand::Chat(ChatMessage {
+            from: from.clone(),
+            text,
+        }));
+        let emote = Emote::Fire;
+        chat.receive(DacttyloCommand::Emote(EmoteMessage { from, emote }));
+        chat.receive(DacttyloCommand::Ready(true));
+
+        let texts: Vec<&str> = chat
+            .log
+            .iter()
+            .map(|line| match line {
+                LogLine::Chat(msg) => msg.text.as_str(),
+                LogLine::Notice(notice) => notice.as_str(),
+            })
+            .collect();
+        assert_eq!(texts, ["hi", "🔥"]);
+    }
+}
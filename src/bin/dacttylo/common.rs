@@ -1,27 +1,40 @@
+use crate::chat::{render_log, Chat};
 use dacttylo::{
     app::{
-        state::{PlayerPool, PlayerState},
-        widget::DacttyloWidget,
+        state::{PlayerPool, PlayerState, PlayerStatus},
+        widget::{DacttyloWidget, Palette},
     },
+    cli::base_opts::BaseOpts,
+    config::Config,
     game::game::Game,
-    highlighting::{Highlighter, SyntectHighlighter},
+    ghost::GHOST_NAME,
+    highlighting::{Highlighter, SyntectHighlighter, TokenFilter, TokenKinds},
+    normalize::{is_untypeable, Normalization},
     stats::GameStats,
+    text_index::TextIndex,
+    text_view::TextViewState,
     utils::{
-        syntect::syntect_load_defaults,
+        color::ColorSupport,
+        syntect::{find_theme, syntect_load_defaults},
         types::{AsyncResult, StyledLine},
     },
-    widgets::{figtext::FigTextWidget, wpm::WpmWidget},
+    widgets::{
+        color_filter::ColorFilter,
+        figtext::FigTextWidget,
+        leaderboard::{LeaderboardEntry, LeaderboardWidget},
+        wpm::WpmWidget,
+    },
 };
 use figlet_rs::FIGfont;
 use once_cell::sync::OnceCell;
-use std::{io::Stdout, time::Duration};
+use std::{io::Stdout, iter, time::Duration};
 use syntect::highlighting::Theme;
 use tui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::StyledGrapheme,
-    widgets::{Block, Borders},
+    widgets::{Block, Borders, Clear},
     Frame, Terminal,
 };
 
@@ -33,13 +46,21 @@ pub enum SessionState {
 pub enum SessionEnd {
     Finished,
     Quit,
+
+    /// The referee ended the session
+    Closed,
 }
 
-pub fn handle_wpm_tick(stats: &mut GameStats, main: &PlayerState) {
-    let recorder = &main.recorder;
+pub fn handle_wpm_tick<O>(game: &mut Game<O>) {
+    let recorder = &game.main.recorder;
+    if !recorder.is_started() || recorder.is_paused() {
+        return;
+    }
+
+    let stats = &mut game.stats;
     let record = recorder.record();
     let elapsed = recorder.elapsed();
-    let wpm = record.wpm_at(Duration::from_secs(4), elapsed);
+    let wpm = record.wpm_at(game.config.wpm_window(), elapsed);
 
     stats.wpm_series.push((elapsed.as_secs_f64(), wpm));
     stats.average_wpm = record.average_wpm(recorder.elapsed());
@@ -48,15 +69,70 @@ pub fn handle_wpm_tick(stats: &mut GameStats, main: &PlayerState) {
     stats.precision = record.precision();
 }
 
-pub fn get_theme(theme: &str) -> &'static Theme {
-    let (_, ts) = syntect_load_defaults();
-    &ts.themes[theme]
+/// Whether an opponent is still in the race, the frames of players
+/// who left or forfeited may still be on their way
+pub fn is_racing(pool: &PlayerPool, name: &str) -> bool {
+    pool.player(name)
+        .is_some_and(|player| player.status() != PlayerStatus::Forfeited)
+}
+
+/// The WPM panel shows the speed of the leading player
+pub fn handle_leader_wpm<O>(game: &mut Game<O>) {
+    if let Some(leader) = game.opponents.leader() {
+        let recorder = &leader.recorder;
+        let elapsed = recorder.elapsed();
+        let wpm = recorder.record().wpm_at(game.config.wpm_window(), elapsed);
+        game.stats.wpm_series.push((elapsed.as_secs_f64(), wpm));
+    }
+}
+
+/// Username from the command line, or from the config file otherwise
+pub fn resolve_username<O: BaseOpts>(
+    opts: &O,
+    config: &Config,
+) -> AsyncResult<String> {
+    opts.get_username()
+        .map(str::to_owned)
+        .or_else(|| config.username.clone())
+        .ok_or_else(|| {
+            "no username provided, use `--username` or set `username` in the config file"
+                .into()
+        })
+}
+
+/// Index of a text skipping the untypeable graphemes and the tokens
+/// excluded by the filter, `kinds` holding the token kinds of each line
+pub fn skip_index<'t>(
+    text: &'t str,
+    kinds: &[Vec<TokenKinds>],
+    filter: TokenFilter,
+    normalize: &Normalization,
+) -> TextIndex<'t> {
+    let mut kinds = kinds.iter().flatten();
+
+    TextIndex::with_skips(text, |_, g| {
+        let kinds = kinds.next().copied().unwrap_or_default();
+        (normalize.skip_untypeable && is_untypeable(g))
+            || filter.skips(g, kinds)
+    })
+}
+
+/// Dim the skipped graphemes, once for the whole session
+pub fn dim_skipped(lines: &mut [StyledLine], index: &TextIndex) {
+    for pos in index.skipped_ranges().into_iter().flatten() {
+        if let Some(coord) = index.coord(pos) {
+            let g = &mut lines[coord.ln][coord.x];
+            g.style = g.style.add_modifier(Modifier::DIM);
+        }
+    }
 }
 
-pub fn render<O>(
+pub fn render<'t, O: BaseOpts>(
     term: &mut Terminal<CrosstermBackend<Stdout>>,
-    game: &Game<O>,
-    styled_lines: &[StyledLine],
+    game: &Game<'t, O>,
+    styled_lines: &[StyledLine<'t>],
+    view: &mut TextViewState<'t>,
+    chat: Option<&Chat>,
 ) -> AsyncResult<()> {
     term.draw(|f| {
         let chunks = Layout::default()
@@ -76,14 +152,43 @@ pub fn render<O>(
             .split(chunks[0]);
         render_dacttylo(f, wpm_chunks[0]);
         render_wpm(f, wpm_chunks[1], &game.stats);
-        render_text(
-            f,
-            chunks[1],
-            &game.main,
-            &game.opponents,
-            styled_lines,
-            &game.theme,
-        );
+
+        // the sidebar is only worth its room with someone to race
+        let text_area = if game.opponents.players().is_empty() {
+            chunks[1]
+        } else {
+            let race_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(
+                    [Constraint::Min(20), Constraint::Length(28)].as_ref(),
+                )
+                .split(chunks[1]);
+            match chat {
+                Some(chat) => {
+                    let sidebar_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints(
+                            [Constraint::Min(5), Constraint::Length(8)]
+                                .as_ref(),
+                        )
+                        .split(race_chunks[1]);
+                    render_leaderboard(f, sidebar_chunks[0], game);
+                    render_log(f, sidebar_chunks[1], chat, "F1-F4 emotes");
+                }
+                None => render_leaderboard(f, race_chunks[1], game),
+            }
+            race_chunks[0]
+        };
+        render_text(f, text_area, game, styled_lines, view);
+
+        if let Some(remaining) = game.countdown_remaining() {
+            render_overlay(f, text_area, &remaining.to_string(), "Get ready");
+        } else if game.is_paused() {
+            let title = format!("{} to resume", game.config.keys.pause);
+            render_overlay(f, text_area, "paused", &title);
+        }
+
+        f.render_widget(ColorFilter::new(ColorSupport::current()), f.size());
     })?;
 
     Ok(())
@@ -117,25 +222,100 @@ pub fn render_wpm(
     f.render_widget(widget, area);
 }
 
-pub fn render_text(
+fn leaderboard_entry(
+    player: &PlayerState,
+    wpm_window: Duration,
+    is_main: bool,
+) -> LeaderboardEntry {
+    let recorder = &player.recorder;
+    let record = recorder.record();
+    let elapsed = record
+        .inputs
+        .last()
+        .map_or(Duration::ZERO, |(elapsed, _)| elapsed.duration);
+
+    // the speed of players out of the race is frozen at their last input
+    let status = player.status();
+    let now = match status {
+        PlayerStatus::Typing => recorder.elapsed(),
+        _ => elapsed,
+    };
+
+    LeaderboardEntry {
+        name: player.name().clone(),
+        status,
+        progress: player.progress(),
+        wpm: record.wpm_at(wpm_window, now),
+        mistakes: record.count_wrong(),
+        elapsed,
+        is_main,
+    }
+}
+
+/// Standing of the main player and of every opponent
+pub fn render_leaderboard<O: BaseOpts>(
     f: &mut Frame<CrosstermBackend<Stdout>>,
     area: Rect,
-    main: &PlayerState<'_>,
-    opponents: &PlayerPool<'_>,
-    styled_lines: &[StyledLine],
-    theme: &str,
+    game: &Game<O>,
 ) {
+    let wpm_window = game.config.wpm_window();
+    let mut entries: Vec<LeaderboardEntry> = game
+        .opponents
+        .players()
+        .values()
+        .map(|player| leaderboard_entry(player, wpm_window, false))
+        .collect();
+    if !game.opts.is_spectator() {
+        entries.push(leaderboard_entry(&game.main, wpm_window, true));
+    }
+
     let block = Block::default()
         .borders(Borders::ALL)
+        .title("Race")
         .style(Style::default().bg(Color::Reset).fg(Color::White));
+    f.render_widget(LeaderboardWidget::new(entries).block(block), area);
+}
 
-    let bg = get_theme(theme).settings.background.unwrap();
+pub fn render_text<'t, O: BaseOpts>(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    game: &Game<'t, O>,
+    styled_lines: &[StyledLine<'t>],
+    view: &mut TextViewState<'t>,
+) {
+    let config = &game.config;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Reset).fg(Color::White));
 
-    f.render_widget(
-        DacttyloWidget::new(main, opponents, styled_lines)
+    let theme = find_theme(&config.theme).ok();
+    let bg = theme
+        .and_then(|theme| theme.settings.background)
+        .map_or(Color::Reset, |bg| Color::Rgb(bg.r, bg.g, bg.b));
+    let palette = match theme {
+        Some(theme) if config.style.match_theme => Palette::from_theme(theme),
+        _ => config.colors.clone(),
+    };
+
+    let mut widget =
+        DacttyloWidget::new(&game.main, &game.opponents, styled_lines);
+    if game.ghost.is_some() {
+        widget = widget.ghost(GHOST_NAME);
+    }
+    if game.opts.is_spectator() {
+        widget = widget.spectate();
+    }
+
+    f.render_stateful_widget(
+        widget
             .block(block)
-            .bg_color(Color::Rgb(bg.r, bg.g, bg.b)),
+            .bg_color(bg)
+            .palette(palette)
+            .style(config.style.clone())
+            .tab_width(config.tab_width)
+            .color_support(ColorSupport::current()),
         area,
+        view,
     );
 }
 
@@ -150,3 +330,37 @@ pub fn render_dacttylo(f: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
         .block(block);
     f.render_widget(figtext, area);
 }
+
+/// Popup with large FIGlet text drawn on top of the given area
+pub fn render_overlay(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    text: &str,
+    title: &str,
+) {
+    let area = centered_rect(area, 60, 8);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .style(Style::default().bg(Color::Reset).fg(Color::White));
+
+    let figtext = FigTextWidget::new(text, load_title_font())
+        .align(Alignment::Center)
+        .block(block);
+
+    f.render_widget(Clear, area);
+    f.render_widget(figtext, area);
+}
+
+/// Rect of at most `width` x `height` cells centered within `area`
+pub fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
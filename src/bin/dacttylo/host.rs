@@ -1,85 +1,145 @@
 use crate::{
+    chat::{emote_for, Chat},
     common::*,
-    protocol::{DacttyloCommand, DacttyloMetadata},
+    lobby::run_host_lobby,
+    protocol::{DacttyloCommand, DacttyloMetadata, FinalResults},
     report::{
-        display_session_report, generate_session_result, Ranking, SessionResult,
+        display_online_report, generate_session_result, rank_players,
+        SessionResult,
     },
+    sync::{vouches_for, InputSync},
 };
 use bincode::{deserialize, serialize};
-use chrono::{DateTime, Utc};
 use crossterm::event::{Event, KeyCode, KeyEvent};
 use dacttylo::{
-    cli::HostOptions,
-    highlighting::{Highlighter, SyntectHighlighter},
+    app::state::{PlayerPool, PlayerState, PlayerStatus},
+    cli::{base_opts::BaseOpts, HostOptions},
+    config::Config,
+    highlighting::{SyntectHighlighter, TokenFilter, TokenKinds},
+    text_view::TextViewState,
     utils::{
         self,
-        syntect::syntect_load_defaults,
+        syntect::{find_syntax_or_plain, find_theme, resolve_syntax},
         types::{AsyncResult, StyledLine},
     },
 };
 use dacttylo::{
     events::AppEvent,
     game::{game::Game, online_game::OnlineGame},
-    session::{
-        self, event::SessionEvent, session_handle::SessionHandle,
-        SessionClient, SessionCommand,
-    },
+    session::{self, event::SessionEvent, SessionClient, SessionCommand},
     utils::{
-        time::{datetime_in, wake_up},
+        time::instant_at,
         tui::{enter_tui_mode, leave_tui_mode},
     },
 };
-use std::{collections::HashMap, io::Stdout, iter, time::Duration};
+use std::{collections::HashMap, io::Stdout, path::Path, time::Duration};
 use syntect::parsing::SyntaxReference;
 use tokio::{
-    fs,
-    io::{self, AsyncBufReadExt},
-    select,
-    time::sleep,
+    fs, select,
+    time::{sleep, sleep_until},
 };
 use tokio_stream::StreamExt;
 use tui::{backend::CrosstermBackend, Terminal};
 
-const THEME: &str = "Solarized (dark)";
-
-pub async fn run_host_session(opts: HostOptions) -> AsyncResult<()> {
-    println!("> Hosting as `{}`", opts.username);
+pub async fn run_host_session(
+    opts: HostOptions,
+    config: Config,
+) -> AsyncResult<()> {
+    let username = resolve_username(&opts, &config)?;
+    if opts.referee {
+        println!("> Refereeing as `{}`", username);
+    } else {
+        println!("> Hosting as `{}`", username);
+    }
 
-    let syntax = find_syntax_for_file(&opts.file)?;
     let text = fs::read_to_string(&opts.file).await?;
+    let text = config.normalize.apply(&text);
+    let syntax = resolve_syntax(&opts.file, &text, opts.syntax.as_deref())?;
+
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let (mut lines, kinds) = highlight(&syntax.name, &config.theme, &lines)?;
+    let filter = TokenFilter::new(&opts.skip, &opts.only);
+    let index = skip_index(&text, &kinds, filter, &config.normalize);
+    dim_skipped(&mut lines, &index);
 
+    let mut session = session::new().await?;
+    println!("Local peer id: {:?}", session.peer_id);
+
+    let file_name = Path::new(&opts.file).file_name();
     let metadata = DacttyloMetadata {
+        file_name: file_name
+            .map_or(opts.file.clone(), |n| n.to_string_lossy().into_owned()),
         syntax_name: syntax.name.clone(),
         text: text.clone(),
+        skipped: index.skipped_ranges(),
+        host_id: session.peer_id.to_base58(),
     };
 
-    let mut session = session::new().await?;
-    println!("Local peer id: {:?}", session.peer_id);
+    session
+        .client
+        .host_session(&username, serialize(&metadata)?)
+        .await?;
 
-    let (start_date, mut registered_users) =
-        take_registrations(&mut session, metadata, &opts).await?;
+    let mut term = enter_tui_mode(std::io::stdout())?;
+    let mut chat = Chat::default();
+    let lobby = run_host_lobby(
+        &mut term,
+        &mut session,
+        &metadata,
+        &username,
+        opts.referee,
+        &config.keys,
+        &mut chat,
+    )
+    .await;
+    let (start_date, mut registered_users) = match lobby {
+        Ok(Some(lock)) => lock,
+        Ok(None) => return leave_tui_mode(term),
+        Err(e) => {
+            leave_tui_mode(term)?;
+            return Err(e);
+        }
+    };
+    let lock = SessionCommand::LockSession {
+        registered_users: registered_users.clone(),
+        session_start: start_date.to_string(),
+    };
 
     registered_users.remove(&session.peer_id.to_base58());
     let opponent_names: Vec<&str> =
         registered_users.values().map(|n| n.as_ref()).collect();
 
-    let app = OnlineGame::new(
+    let idle_timeout = Some(Duration::from_secs(opts.idle_timeout))
+        .filter(|timeout| !timeout.is_zero());
+    let mut app = OnlineGame::new(
         session,
-        Game::new(&text, &opponent_names, opts, THEME)?,
+        Game::with_index(index, &opponent_names, opts, config)?,
     );
 
-    let lines: Vec<&str> = text.split_inclusive('\n').collect();
-    let lines = highlight(&syntax.name, THEME, &lines)?;
-
-    wake_up(Some(start_date)).await;
-
-    let mut term = enter_tui_mode(std::io::stdout())?;
-    let session_result =
-        handle_events(&mut term, registered_users, app, &lines).await;
+    app.game.start_countdown(instant_at(start_date));
+
+    let session_result = handle_events(
+        &mut term,
+        registered_users,
+        &mut app,
+        &lines,
+        Role::Host {
+            lock: &lock,
+            idle_timeout,
+        },
+        &mut chat,
+    )
+    .await;
 
     let result = match session_result {
         Ok(Some(session_result)) => {
-            display_session_report(&mut term, session_result).await
+            display_online_report(
+                &mut term,
+                session_result,
+                &mut app,
+                &mut chat,
+            )
+            .await
         }
         Ok(None) => Ok(()),
         Err(e) => Err(e),
@@ -89,158 +149,418 @@ pub async fn run_host_session(opts: HostOptions) -> AsyncResult<()> {
     result
 }
 
-async fn take_registrations(
-    session: &mut SessionHandle,
-    metadata: DacttyloMetadata,
-    opts: &HostOptions,
-) -> AsyncResult<(DateTime<Utc>, HashMap<String, String>)> {
-    session
-        .client
-        .host_session(&opts.username, serialize(&metadata)?)
-        .await?;
-    let mut registered_users: HashMap<String, String> = Default::default();
-    registered_users.insert(session.peer_id.to_base58(), opts.username.clone());
-    let mut stdin = io::BufReader::new(io::stdin()).lines();
-
-    loop {
-        select! {
-            // handle user input
-            line = stdin.next_line() => {
-                let _line = line?.expect("Standard input was closed");
-                let date = lock_registrations(&mut session.client, registered_users.clone()).await?;
-                return Ok((date, registered_users));
-            }
-            // handle session events
-            event = session.events.next() => {
-                let event = event.ok_or("event stream closed unexpectedly")?;
-                let SessionEvent {
-                    peer_id, cmd
-                } = event.into();
-
-                if let SessionCommand::Register { user } = cmd {
-                    registered_users.entry(peer_id).or_insert_with(|| {
-                        println!("Registering user `{}`", user);
-                        user
-                    });
-                };
-            }
-        };
-    }
-}
-
-async fn lock_registrations(
-    client: &mut SessionClient,
-    registered_users: HashMap<String, String>,
-) -> AsyncResult<DateTime<Utc>> {
-    let date = datetime_in(chrono::Duration::seconds(3)).unwrap();
-    let lock_cmd = SessionCommand::LockSession {
-        registered_users: registered_users.clone(),
-        session_start: date.to_string(),
-    };
-
-    println!("Locking session...");
-    client.publish(lock_cmd).await?;
-    println!("Session locked, starting soon :)");
-
-    Ok(date)
-}
-
-pub fn find_syntax_for_file(
-    file: &str,
-) -> AsyncResult<&'static SyntaxReference> {
-    let (syntax_set, _) = syntect_load_defaults();
-    syntax_set
-        .find_syntax_for_file(file)
-        .map_err(|_| "error reading file")?
-        .ok_or_else(|| "failed to find syntax".into())
-}
-
 pub fn highlight<'t>(
     name: &str,
     theme: &str,
     lines: &[&'t str],
-) -> AsyncResult<Vec<StyledLine<'t>>> {
+) -> AsyncResult<(Vec<StyledLine<'t>>, Vec<Vec<TokenKinds>>)> {
     let hl = SyntectHighlighter::new()
-        .from_syntax(name)?
-        .theme(get_theme(theme))
+        .syntax(find_syntax_or_plain(name))
+        .theme(find_theme(theme)?)
         .build()?;
 
-    Ok(hl.highlight(lines))
+    Ok(hl.highlight_scoped(lines))
+}
+
+/// Part taken in the race by the local peer
+#[derive(Clone, Copy)]
+pub enum Role<'a> {
+    /// Hosting the session, with the command sent to spectators and
+    /// the time after which idle players are disqualified
+    Host {
+        lock: &'a SessionCommand,
+        idle_timeout: Option<Duration>,
+    },
+
+    /// Racing in the session hosted by the given peer
+    Player { host_id: &'a str },
+}
+
+/// What the race loop keeps track of besides the game
+struct RaceState<'r> {
+    role: Role<'r>,
+    registered_users: HashMap<String, String>,
+    sync: InputSync,
+    results: Option<FinalResults>,
+    chat: &'r mut Chat,
+
+    /// Whether the host left before publishing the results, the
+    /// players then rank the race themselves
+    host_left: bool,
 }
 
-pub async fn handle_events<O>(
+/// Race loop shared by the host and the players. The host publishes
+/// the results once every player is done, a refereeing host watches
+/// the race and decides when it ends. Emotes are sent with function
+/// keys and shown in the chat of the sidebar.
+pub async fn handle_events<'t, O: BaseOpts>(
     term: &mut Terminal<CrosstermBackend<Stdout>>,
-    mut registered_users: HashMap<String, String>,
-    mut app: OnlineGame<'_, O>,
-    lines: &[StyledLine<'_>],
+    registered_users: HashMap<String, String>,
+    app: &mut OnlineGame<'t, O>,
+    lines: &[StyledLine<'t>],
+    role: Role<'_>,
+    chat: &mut Chat,
 ) -> AsyncResult<Option<SessionResult>> {
+    let mut view = TextViewState::new();
+    let mut race = RaceState {
+        role,
+        registered_users,
+        sync: InputSync::new(),
+        results: None,
+        chat,
+        host_left: false,
+    };
+
     loop {
+        let frame_deadline = race.sync.frame_deadline().into();
         let event = select! {
             Some(event) = app.game.events.next() => event,
-            Some(event) = app.session.events.next() => event.into()
+            Some(event) = app.session.events.next() => event.into(),
+            _ = sleep_until(frame_deadline), if race.sync.has_queued() => {
+                send_inputs(&mut app.session.client, &mut race.sync).await?;
+                continue;
+            }
         };
 
-        let session_state =
-            handle_event(event, &mut registered_users, &mut app).await?;
+        let session_state = handle_event(event, app, &mut race).await?;
 
         if let SessionState::End(end) = session_state {
             // NOTE: last floodsub publish may not have been sent yet,
             // small delay to prevent the task from dropping too soon on process exit
             sleep(Duration::from_millis(10)).await;
 
-            if let SessionEnd::Finished = &end {
-                return Ok(Some(generate_session_result(app.game)));
-            } else {
-                return Ok(None);
-            }
+            return Ok(match end {
+                SessionEnd::Quit => None,
+                _ => Some(generate_session_result(&app.game, race.results)),
+            });
         }
 
-        render(term, &app.game, lines)?;
+        render(term, &app.game, lines, &mut view, Some(&*race.chat))?;
     }
 }
 
-async fn handle_event<O>(
+async fn handle_event<O: BaseOpts>(
     event: AppEvent,
-    registered_users: &mut HashMap<String, String>,
     app: &mut OnlineGame<'_, O>,
+    race: &mut RaceState<'_>,
 ) -> AsyncResult<SessionState> {
+    // quick reactions go out whatever the player is doing
+    if let AppEvent::Term(Ok(Event::Key(key))) = &event {
+        if let Some(emote) = emote_for(key) {
+            let name = app.game.main.name().clone();
+            let client = &mut app.session.client;
+            race.chat.send_emote(client, &name, emote).await?;
+            return Ok(SessionState::Ongoing);
+        }
+    }
+
     match event {
-        AppEvent::Term(e) => handle_term(e?, app).await,
-        AppEvent::Session(e) => {
-            handle_session_event(e, registered_users, &mut app.game)
+        AppEvent::Term(e) if app.game.opts.is_spectator() => {
+            handle_referee_term(e?, app, &mut race.results).await
+        }
+        AppEvent::Term(e) => {
+            match handle_term(e?, app, &mut race.sync).await? {
+                SessionState::Ongoing => check_race_end(app, race).await,
+                end => Ok(end),
+            }
+        }
+        AppEvent::Session(SessionEvent::Command {
+            cmd: SessionCommand::Spectate,
+            ..
+        }) => {
+            if let Role::Host { lock, .. } = race.role {
+                welcome_spectator(lock, app, &race.sync).await?;
+            }
+            Ok(SessionState::Ongoing)
         }
+        AppEvent::Session(e) => match handle_session_event(e, app, race).await?
+        {
+            SessionState::Ongoing => check_race_end(app, race).await,
+            end => Ok(end),
+        },
         AppEvent::WpmTick => {
-            handle_wpm_tick(&mut app.game.stats, &app.game.main);
+            if app.game.opts.is_spectator() {
+                handle_leader_wpm(&mut app.game);
+            } else {
+                handle_wpm_tick(&mut app.game);
+                if app.game.is_started() {
+                    // the sync counts the queued inputs, which go first
+                    let client = &mut app.session.client;
+                    send_inputs(client, &mut race.sync).await?;
+                    let state = race.sync.local_sync(&app.game.main);
+                    client.push(&DacttyloCommand::Sync(state)).await?;
+                }
+            }
+
+            if let Role::Host {
+                idle_timeout: Some(timeout),
+                ..
+            } = race.role
+            {
+                disqualify_idle_players(app, timeout).await?;
+                return check_race_end(app, race).await;
+            }
+            Ok(SessionState::Ongoing)
+        }
+        AppEvent::Tick => {
+            app.game.update_countdown()?;
             Ok(SessionState::Ongoing)
         }
         _ => Ok(SessionState::Ongoing),
     }
 }
 
-fn handle_session_event<O>(
+/// Send the players and the state of the race to a new spectator
+async fn welcome_spectator<O: BaseOpts>(
+    lock: &SessionCommand,
+    app: &mut OnlineGame<'_, O>,
+    sync: &InputSync,
+) -> AsyncResult<()> {
+    let game = &app.game;
+    let main = Some(&game.main)
+        .filter(|_| !game.opts.is_spectator())
+        .map(|main| sync.local_snapshot(main));
+    let snapshot = game
+        .opponents
+        .players()
+        .values()
+        .map(|player| sync.snapshot(player))
+        .chain(main)
+        .collect();
+
+    let client = &mut app.session.client;
+    client.publish(lock.clone()).await?;
+    client.push(&DacttyloCommand::Snapshot(snapshot)).await
+}
+
+/// Send the inputs of the local player gathered since the last frame
+async fn send_inputs(
+    client: &mut SessionClient,
+    sync: &mut InputSync,
+) -> AsyncResult<()> {
+    match sync.take_frame() {
+        Some(frame) => client.push(&DacttyloCommand::Inputs(frame)).await,
+        None => Ok(()),
+    }
+}
+
+/// Players who did not type for a while do not finish the race,
+/// the host included, so that it ends even if someone walked away
+async fn disqualify_idle_players<O: BaseOpts>(
+    app: &mut OnlineGame<'_, O>,
+    timeout: Duration,
+) -> AsyncResult<()> {
+    let game = &mut app.game;
+    if !game.is_started() {
+        return Ok(());
+    }
+
+    let main = Some(&game.main).filter(|_| !game.opts.is_spectator());
+    let players = game.opponents.players().values().chain(main);
+    let client = &mut app.session.client;
+    for name in idle_players(players, timeout) {
+        if !game.opts.is_spectator() && &name == game.main.name() {
+            game.main.forfeit();
+        } else {
+            game.opponents.forfeit(&name).ok();
+        }
+        client.push(&DacttyloCommand::Disqualify(name)).await?;
+    }
+
+    Ok(())
+}
+
+/// Names of the players still typing who did not type for `timeout`
+fn idle_players<'a, 't: 'a>(
+    players: impl Iterator<Item = &'a PlayerState<'t>>,
+    timeout: Duration,
+) -> Vec<String> {
+    players
+        .filter(|player| player.status() == PlayerStatus::Typing)
+        .filter(|player| player.idle_time() >= timeout)
+        .map(|player| player.name().clone())
+        .collect()
+}
+
+/// A player who left the session does not finish the race,
+/// returns its name if it was registered
+fn handle_departure<O>(
+    peer_id: &str,
+    game: &mut Game<'_, O>,
+    race: &mut RaceState<'_>,
+) -> Option<String> {
+    if let Role::Player { host_id } = race.role {
+        race.host_left |= host_id == peer_id;
+    }
+
+    let name = race.registered_users.remove(peer_id)?;
+    game.opponents.forfeit(&name).ok();
+    Some(name)
+}
+
+async fn handle_session_event<O: BaseOpts>(
     event: SessionEvent,
-    registered_users: &mut HashMap<String, String>,
-    game: &mut Game<O>,
+    app: &mut OnlineGame<'_, O>,
+    race: &mut RaceState<'_>,
 ) -> AsyncResult<SessionState> {
-    let SessionEvent { peer_id, cmd } = event;
+    let (peer_id, cmd) = match event {
+        SessionEvent::Command { peer_id, cmd } => (peer_id, cmd),
+        SessionEvent::PeerLeft { peer_id } => {
+            let name = handle_departure(&peer_id, &mut app.game, race);
+
+            // peers that can still reach the player may not notice
+            if let (Some(name), Role::Host { .. }) = (name, race.role) {
+                let cmd = DacttyloCommand::Disqualify(name);
+                app.session.client.push(&cmd).await?;
+            }
+            return Ok(SessionState::Ongoing);
+        }
+    };
 
-    if let SessionCommand::Push(payload) = cmd {
-        let username = registered_users
-            .get(&peer_id)
-            .ok_or("session event origin user not found")?;
+    // only the host referees the race
+    let host_id = match race.role {
+        Role::Player { host_id } => Some(host_id),
+        Role::Host { .. } => None,
+    };
+    let from_host = host_id == Some(peer_id.as_str());
+
+    match cmd {
+        SessionCommand::Push(payload) => {
+            // payloads that fail to decode, such as from another version,
+            // are skipped
+            let cmd = match deserialize(&payload) {
+                Ok(cmd) => cmd,
+                Err(_) => return Ok(SessionState::Ongoing),
+            };
+            if let DacttyloCommand::Forfeit = cmd {
+                handle_departure(&peer_id, &mut app.game, race);
+                return Ok(SessionState::Ongoing);
+            }
 
-        match deserialize(&payload)? {
-            DacttyloCommand::Input(ch) => {
-                game.opponents.process_input(username, ch).ok();
+            // a refereeing host is not registered, it only sends results
+            let game = &mut app.game;
+            let username = race
+                .registered_users
+                .get(&peer_id)
+                .filter(|name| is_racing(&game.opponents, name));
+            let client = &mut app.session.client;
+            let sync = &mut race.sync;
+
+            match cmd {
+                DacttyloCommand::Inputs(frame) => {
+                    if let Some(username) = username {
+                        let pool = &mut game.opponents;
+                        if sync.receive(pool, username, &frame) {
+                            let cmd = DacttyloCommand::Resync(username.clone());
+                            client.push(&cmd).await?;
+                        }
+                    }
+                }
+                DacttyloCommand::Sync(state) => {
+                    if let Some(username) = username {
+                        if sync.diverged(&game.opponents, username, &state) {
+                            let cmd = DacttyloCommand::Resync(username.clone());
+                            client.push(&cmd).await?;
+                        }
+                    }
+                }
+                DacttyloCommand::Resync(name)
+                    if !game.opts.is_spectator()
+                        && &name == game.main.name() =>
+                {
+                    let snapshot = sync.local_snapshot(&game.main);
+                    let cmd = DacttyloCommand::Snapshot(vec![snapshot]);
+                    client.push(&cmd).await?;
+                }
+                DacttyloCommand::Snapshot(players) => {
+                    let users = &race.registered_users;
+                    for player in &players {
+                        if vouches_for(users, host_id, &peer_id, &player.name) {
+                            sync.restore(&mut game.opponents, player);
+                        }
+                    }
+                }
+                DacttyloCommand::Disqualify(name) if from_host => {
+                    if !game.opts.is_spectator() && &name == game.main.name() {
+                        game.main.forfeit();
+                    } else {
+                        game.opponents.forfeit(&name).ok();
+                    }
+                }
+                DacttyloCommand::Chat(msg) => race.chat.push(msg),
+                DacttyloCommand::Emote(msg) => race.chat.push(msg.into()),
+                // forfeits are handled above, lobby messages may arrive late
+                _ => (),
             }
-            DacttyloCommand::Forfeit => {
-                game.opponents.remove(username);
-                registered_users.remove(&peer_id);
+        }
+        SessionCommand::EndSession(payload) if from_host => {
+            if let Ok(results) = deserialize(&payload) {
+                race.results = Some(results);
+                return Ok(SessionState::End(SessionEnd::Closed));
             }
         }
+        _ => (),
+    }
+
+    Ok(SessionState::Ongoing)
+}
+
+/// Once every player is done the host publishes the results, which
+/// the players wait for unless the host left the race
+async fn check_race_end<O: BaseOpts>(
+    app: &mut OnlineGame<'_, O>,
+    race: &mut RaceState<'_>,
+) -> AsyncResult<SessionState> {
+    let game = &app.game;
+    let main = Some(&game.main).filter(|_| !game.opts.is_spectator());
+    if !race_done(main, &game.opponents) {
+        return Ok(SessionState::Ongoing);
+    }
 
-        if game.main.is_done() && game.opponents.are_done() {
-            return Ok(SessionState::End(SessionEnd::Finished));
+    match race.role {
+        Role::Host { .. } => close_session(app, &mut race.results).await,
+        Role::Player { .. } if race.host_left => {
+            Ok(SessionState::End(SessionEnd::Finished))
+        }
+        Role::Player { .. } => Ok(SessionState::Ongoing),
+    }
+}
+
+/// Whether every player is done, the local player unless refereeing
+/// and every registered opponent
+fn race_done(main: Option<&PlayerState>, opponents: &PlayerPool) -> bool {
+    let main_done =
+        main.is_none_or(|main| main.status() != PlayerStatus::Typing);
+    main_done && opponents.are_done()
+}
+
+/// Rank the players and end the session for everyone with the
+/// same results
+async fn close_session<O: BaseOpts>(
+    app: &mut OnlineGame<'_, O>,
+    results: &mut Option<FinalResults>,
+) -> AsyncResult<SessionState> {
+    let final_results = rank_players(&app.game);
+    let serial = serialize(&final_results)?;
+    app.session
+        .client
+        .publish(SessionCommand::EndSession(serial))
+        .await?;
+
+    *results = Some(final_results);
+    Ok(SessionState::End(SessionEnd::Closed))
+}
+
+/// The referee does not type, the quit key ends the session for
+/// everyone and publishes the ranking of the players who finished
+async fn handle_referee_term<O: BaseOpts>(
+    term_event: crossterm::event::Event,
+    app: &mut OnlineGame<'_, O>,
+    results: &mut Option<FinalResults>,
+) -> AsyncResult<SessionState> {
+    if let Event::Key(event) = term_event {
+        if app.game.config.keys.quit.matches(&event) {
+            return close_session(app, results).await;
         }
     }
 
@@ -250,17 +570,19 @@ fn handle_session_event<O>(
 async fn handle_term<O>(
     term_event: crossterm::event::Event,
     app: &mut OnlineGame<'_, O>,
+    sync: &mut InputSync,
 ) -> AsyncResult<SessionState> {
     let client = &mut app.session.client;
 
     if let Event::Key(event) = term_event {
-        let KeyEvent { code, .. } = event;
-        let c = match code {
-            KeyCode::Esc => {
-                let serial = serialize(&DacttyloCommand::Forfeit)?;
-                client.publish(SessionCommand::Push(serial)).await.unwrap();
+        let c = match event.code {
+            _ if app.game.config.keys.quit.matches(&event) => {
+                send_inputs(client, sync).await?;
+                client.push(&DacttyloCommand::Forfeit).await?;
                 return Ok(SessionState::End(SessionEnd::Quit));
             }
+            _ if !app.game.is_started() => None,
+            _ if app.game.main.status() == PlayerStatus::Forfeited => None,
             KeyCode::Char(c) => Some(c),
             KeyCode::Enter => Some('\n'),
             KeyCode::Tab => Some('\t'),
@@ -268,16 +590,66 @@ async fn handle_term<O>(
         };
 
         if let Some(c) = c {
-            let serial = serialize(&DacttyloCommand::Input(c))?;
-            client.publish(SessionCommand::Push(serial)).await.unwrap();
-
-            app.game.main.process_input(c);
-
-            if app.game.main.is_done() && app.game.opponents.are_done() {
-                return Ok(SessionState::End(SessionEnd::Finished));
+            // opponents record the input at the time it was typed here
+            let elapsed = app.game.main.recorder.elapsed();
+            sync.queue(c, elapsed);
+            app.game.main.process_input_at(c, elapsed);
+
+            // no need to wait for the end of the frame past the finish line
+            if app.game.main.is_done() {
+                send_inputs(client, sync).await?;
             }
         }
     }
 
     Ok(SessionState::Ongoing)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dacttylo::text_index::TextIndex;
+    use std::{sync::Arc, time::Instant};
+
+    #[test]
+    fn idle_players_disqualified() {
+        let index = Arc::new(TextIndex::new("abc"));
+        let mut pool = PlayerPool::new(index).with_players(&["al", "bo"]);
+        pool.start_at(Instant::now() - Duration::from_secs(90));
+        let timeout = Duration::from_secs(60);
+        let mut idle = idle_players(pool.players().values(), timeout);
+        idle.sort();
+        assert_eq!(idle, ["al", "bo"]);
+
+        let typed = Duration::from_secs(85);
+        pool.process_input_at("al", 'a', typed).unwrap();
+        assert_eq!(idle_players(pool.players().values(), timeout), ["bo"]);
+
+        // players out of the race are not disqualified twice
+        pool.forfeit("bo").unwrap();
+        assert!(idle_players(pool.players().values(), timeout).is_empty());
+        assert!(!is_racing(&pool, "bo"));
+        assert!(!is_racing(&pool, "zoe"));
+        assert!(is_racing(&pool, "al"));
+    }
+
+    #[test]
+    fn race_ends_once_everyone_is_done() {
+        let index = Arc::new(TextIndex::new("a"));
+        let mut pool =
+            PlayerPool::new(index.clone()).with_players(&["al", "bo"]);
+        let mut main = PlayerState::new("me".into(), index);
+        pool.start();
+        main.recorder.start();
+
+        pool.process_input("al", 'a').unwrap();
+        assert!(!race_done(None, &pool));
+        pool.forfeit("bo").unwrap();
+        assert!(race_done(None, &pool));
+
+        // the host races as well unless refereeing
+        assert!(!race_done(Some(&main), &pool));
+        main.process_input('a').unwrap();
+        assert!(race_done(Some(&main), &pool));
+    }
+}
@@ -1,116 +1,113 @@
 use crate::{
+    chat::Chat,
     common::*,
-    host::{handle_events, highlight},
-    join,
-    protocol::{DacttyloCommand, DacttyloMetadata},
-    report::{display_session_report, Ranking, SessionResult},
+    host::{handle_events, highlight, Role},
+    lobby::run_player_lobby,
+    protocol::DacttyloMetadata,
+    report::display_online_report,
 };
-use bincode::{deserialize, serialize};
-use chrono::{DateTime, Utc};
-use crossterm::event::{Event, KeyCode, KeyEvent};
+use bincode::deserialize;
 use dacttylo::{
-    cli::{HostOptions, JoinOptions},
-    session::SessionData,
-    utils::types::AsyncResult,
-};
-use dacttylo::{
-    events::AppEvent,
+    cli::JoinOptions,
+    config::Config,
     game::{game::Game, online_game::OnlineGame},
-    session::{
-        self, event::SessionEvent, session_handle::SessionHandle,
-        SessionClient, SessionCommand,
-    },
+    session::{self, SessionData},
+    text_index::TextIndex,
     utils::{
-        time::{datetime_in, wake_up},
+        syntect::find_syntax,
+        time::instant_at,
         tui::{enter_tui_mode, leave_tui_mode},
+        types::AsyncResult,
     },
 };
-use std::{collections::HashMap, io::Stdout, iter, time::Duration};
-use tokio::{
-    fs,
-    io::{self, AsyncBufReadExt},
-    select,
-};
-use tokio_stream::StreamExt;
-use tui::{backend::CrosstermBackend, Terminal};
 
-const THEME: &str = "Solarized (dark)";
+pub async fn run_join_session(
+    join_opts: JoinOptions,
+    config: Config,
+) -> AsyncResult<()> {
+    let username = resolve_username(&join_opts, &config)?;
+    println!("> Joining as `{}`", username);
 
-async fn register(
-    session: &mut SessionHandle,
-    opts: &JoinOptions,
-) -> AsyncResult<(DacttyloMetadata, DateTime<Utc>, HashMap<String, String>)> {
-    let client = &mut session.client;
+    let mut session = session::new().await?;
+    println!("Local peer id: {:?}", session.peer_id);
 
     let SessionData {
         session_id,
         metadata,
-    } = client.await_session_for_host(&opts.host).await;
-    let metadata = deserialize(&metadata)?;
+    } = session.client.await_session_for_host(&join_opts.host).await;
+    let metadata: DacttyloMetadata = deserialize(&metadata)?;
 
     println!("Session found!");
-    client.join_session(session_id.clone()).await?;
-    println!("Joined session `{}`", session_id.clone());
+    session.client.join_session(session_id.clone()).await?;
+    println!("Joined session `{}`", session_id);
 
-    client
-        .publish(SessionCommand::Register {
-            user: opts.username.clone(),
-        })
-        .await?;
-    println!("Submitted registration...");
-
-    loop {
-        select! {
-            // handle session events
-            event = session.events.next() => {
-                let event = event.ok_or("event stream closed unexpectedly")?;
-                let SessionEvent {
-                    peer_id, cmd
-                } = event.into();
-
-                if let SessionCommand::LockSession { registered_users, session_start } = cmd {
-                    let session_start: DateTime<Utc> = session_start.parse().map_err(|_| "invalid date time for session start")?;
-                    return Ok((metadata, session_start, registered_users));
-                }
-            }
-        };
+    let lines: Vec<&str> = metadata.text.split_inclusive('\n').collect();
+    if find_syntax(&metadata.syntax_name).is_err() {
+        println!(
+            "Syntax `{}` is not available, falling back to plain text",
+            metadata.syntax_name
+        );
     }
-}
-
-pub async fn run_join_session(join_opts: JoinOptions) -> AsyncResult<()> {
-    println!("> Joining as `{}`", join_opts.username);
+    let (mut lines, _) =
+        highlight(&metadata.syntax_name, &config.theme, &lines)?;
 
-    let mut session = session::new().await?;
-    println!("Local peer id: {:?}", session.peer_id);
-
-    let (metadata, start_date, mut registered_users) =
-        register(&mut session, &join_opts).await?;
-
-    let delay = start_date.signed_duration_since(Utc::now());
-    let delay = chrono::Duration::to_std(&delay).unwrap();
-    println!("Session locked! Starting in {:?}...", delay);
+    let mut term = enter_tui_mode(std::io::stdout())?;
+    let mut chat = Chat::default();
+    let lobby = run_player_lobby(
+        &mut term,
+        &mut session,
+        &metadata,
+        &username,
+        &config.keys,
+        &mut chat,
+    )
+    .await;
+    let (start_date, mut registered_users) = match lobby {
+        Ok(Some(lock)) => lock,
+        Ok(None) => return leave_tui_mode(term),
+        Err(e) => {
+            leave_tui_mode(term)?;
+            return Err(e);
+        }
+    };
 
     registered_users.remove(&session.peer_id.to_base58());
     let opponent_names: Vec<&str> =
         registered_users.values().map(|n| n.as_ref()).collect();
 
-    let game = OnlineGame::new(
+    let index =
+        TextIndex::with_skipped_ranges(&metadata.text, &metadata.skipped);
+    let mut game = OnlineGame::new(
         session,
-        Game::new(&metadata.text, &opponent_names, join_opts, THEME)?,
+        Game::with_index(index, &opponent_names, join_opts, config)?,
     );
 
-    let lines: Vec<&str> = metadata.text.split_inclusive('\n').collect();
-    let lines = highlight(&metadata.syntax_name, THEME, &lines)?;
+    dim_skipped(&mut lines, game.game.main.index());
 
-    wake_up(Some(start_date)).await;
+    game.game.start_countdown(instant_at(start_date));
 
-    let mut term = enter_tui_mode(std::io::stdout())?;
-    let session_result =
-        handle_events(&mut term, registered_users, game, &lines).await;
+    let role = Role::Player {
+        host_id: &metadata.host_id,
+    };
+    let session_result = handle_events(
+        &mut term,
+        registered_users,
+        &mut game,
+        &lines,
+        role,
+        &mut chat,
+    )
+    .await;
 
     let result = match session_result {
         Ok(Some(session_result)) => {
-            display_session_report(&mut term, session_result).await
+            display_online_report(
+                &mut term,
+                session_result,
+                &mut game,
+                &mut chat,
+            )
+            .await
         }
         Ok(None) => Ok(()),
         Err(e) => Err(e),
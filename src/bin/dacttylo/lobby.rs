@@ -0,0 +1,624 @@
+use crate::{
+    chat::{render_input, render_log, Chat},
+    protocol::{DacttyloCommand, DacttyloMetadata, LobbyPlayer, LobbyState},
+};
+use bincode::deserialize;
+use chrono::{DateTime, Utc};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent};
+use dacttylo::{
+    config::KeyBindings,
+    events::AppEvent,
+    session::{
+        event::SessionEvent, session_handle::SessionHandle, SessionClient,
+        SessionCommand,
+    },
+    text_index::TextIndex,
+    utils::{color::ColorSupport, time::datetime_in, types::AsyncResult},
+    widgets::color_filter::ColorFilter,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Stdout,
+    time::Duration,
+};
+use tokio::{select, time::interval};
+use tokio_stream::StreamExt;
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+
+/// Start date and registered players, as sent to every peer when
+/// the host starts the race
+pub type Lock = (DateTime<Utc>, HashMap<String, String>);
+
+/// What a key pressed in the lobby asks for, once the chat input
+/// and the selection are taken care of
+enum LobbyAction {
+    None,
+    Send(String),
+    ToggleReady,
+    Kick,
+    Start,
+    Quit,
+}
+
+/// Waiting room shown to the host and to the players until the
+/// host starts the race
+struct Lobby {
+    peer_id: String,
+    is_host: bool,
+    state: LobbyState,
+    selected: usize,
+
+    /// File name, language and length of the text
+    summary: String,
+}
+
+impl Lobby {
+    fn new(
+        peer_id: String,
+        is_host: bool,
+        host: &str,
+        metadata: &DacttyloMetadata,
+    ) -> Self {
+        let index =
+            TextIndex::with_skipped_ranges(&metadata.text, &metadata.skipped);
+        let skipped: usize = metadata.skipped.iter().map(|r| r.len()).sum();
+        let summary = format!(
+            "{}  |  {}  |  {} lines, {} characters to type",
+            metadata.file_name,
+            metadata.syntax_name,
+            index.line_count(),
+            index.len() - skipped
+        );
+
+        Self {
+            peer_id,
+            is_host,
+            state: LobbyState {
+                host: host.to_owned(),
+                players: vec![],
+            },
+            selected: 0,
+            summary,
+        }
+    }
+
+    fn player(&self, peer_id: &str) -> Option<&LobbyPlayer> {
+        self.state.players.iter().find(|p| p.peer_id == peer_id)
+    }
+
+    fn player_mut(&mut self, peer_id: &str) -> Option<&mut LobbyPlayer> {
+        self.state.players.iter_mut().find(|p| p.peer_id == peer_id)
+    }
+
+    fn remove(&mut self, peer_id: &str) -> Option<LobbyPlayer> {
+        let pos = self.state.players.iter().position(|p| p.peer_id == peer_id);
+        let player = self.state.players.remove(pos?);
+        self.selected = self
+            .selected
+            .min(self.state.players.len().saturating_sub(1));
+        Some(player)
+    }
+
+    /// Toggles whether the local player is ready, returns the new
+    /// state unless the local player is not registered
+    fn toggle_ready(&mut self) -> Option<bool> {
+        let peer_id = self.peer_id.clone();
+        let player = self.player_mut(&peer_id)?;
+        player.ready = !player.ready;
+        Some(player.ready)
+    }
+
+    /// Peer id of the selected player, unless it is the local one
+    fn selected_opponent(&self) -> Option<String> {
+        let player = self.state.players.get(self.selected)?;
+        Some(player.peer_id.clone()).filter(|id| id != &self.peer_id)
+    }
+
+    /// Whether the race can start, someone has to race and everyone
+    /// has to be ready
+    fn can_start(&self) -> bool {
+        let players = &self.state.players;
+        !players.is_empty() && players.iter().all(|p| p.ready)
+    }
+
+    fn registered_users(&self) -> HashMap<String, String> {
+        self.state
+            .players
+            .iter()
+            .map(|p| (p.peer_id.clone(), p.name.clone()))
+            .collect()
+    }
+
+    fn handle_key(
+        &mut self,
+        key: KeyEvent,
+        keys: &KeyBindings,
+        chat: &mut Chat,
+    ) -> LobbyAction {
+        match key.code {
+            _ if keys.quit.matches(&key) => LobbyAction::Quit,
+            _ if keys.ready.matches(&key) => LobbyAction::ToggleReady,
+            _ if keys.start.matches(&key) => LobbyAction::Start,
+            _ if keys.kick.matches(&key) => LobbyAction::Kick,
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                LobbyAction::None
+            }
+            KeyCode::Down => {
+                let last = self.state.players.len().saturating_sub(1);
+                self.selected = (self.selected + 1).min(last);
+                LobbyAction::None
+            }
+            _ => chat
+                .handle_key(&key)
+                .map_or(LobbyAction::None, LobbyAction::Send),
+        }
+    }
+}
+
+/// Lobby of the host, which lists the players as they register and
+/// starts the race once all of them are ready. Returns nothing if the
+/// host left.
+pub async fn run_host_lobby(
+    term: &mut Terminal<CrosstermBackend<Stdout>>,
+    session: &mut SessionHandle,
+    metadata: &DacttyloMetadata,
+    username: &str,
+    referee: bool,
+    keys: &KeyBindings,
+    chat: &mut Chat,
+) -> AsyncResult<Option<Lock>> {
+    let peer_id = session.peer_id.to_base58();
+    let mut lobby = Lobby::new(peer_id.clone(), true, username, metadata);
+    if !referee {
+        lobby.state.players.push(LobbyPlayer {
+            peer_id,
+            name: username.to_owned(),
+            ready: false,
+        });
+    }
+    let mut kicked: HashSet<String> = Default::default();
+    let mut input_stream = EventStream::new();
+    let client = &mut session.client;
+
+    loop {
+        render_lobby(term, &lobby, keys, chat)?;
+
+        let event = select! {
+            Some(event) = input_stream.next() => AppEvent::Term(event),
+            Some(event) = session.events.next() => event.into(),
+        };
+
+        match event {
+            AppEvent::Term(event) => {
+                let key = match event? {
+                    Event::Key(key) => key,
+                    _ => continue,
+                };
+
+                match lobby.handle_key(key, keys, chat) {
+                    LobbyAction::Send(text) => {
+                        chat.send(client, username, text).await?;
+                    }
+                    LobbyAction::ToggleReady => {
+                        if lobby.toggle_ready().is_some() {
+                            publish_lobby(client, &lobby).await?;
+                        }
+                    }
+                    LobbyAction::Kick => {
+                        let peer_id = match lobby.selected_opponent() {
+                            Some(peer_id) => peer_id,
+                            None => continue,
+                        };
+                        if let Some(player) = lobby.remove(&peer_id) {
+                            let cmd = DacttyloCommand::Kick(peer_id.clone());
+                            client.push(&cmd).await?;
+                            publish_lobby(client, &lobby).await?;
+                            chat.notice(format!("{} was kicked", player.name));
+                            kicked.insert(peer_id);
+                        }
+                    }
+                    LobbyAction::Start if lobby.can_start() => {
+                        let registered_users = lobby.registered_users();
+                        let date = lock_registrations(
+                            client,
+                            registered_users.clone(),
+                        )
+                        .await?;
+                        return Ok(Some((date, registered_users)));
+                    }
+                    LobbyAction::Start => {
+                        chat.notice(
+                            "Waiting for every player to be ready".into(),
+                        );
+                    }
+                    LobbyAction::Quit => return Ok(None),
+                    LobbyAction::None => (),
+                }
+            }
+            AppEvent::Session(SessionEvent::PeerLeft { peer_id }) => {
+                if let Some(player) = lobby.remove(&peer_id) {
+                    chat.notice(format!("{} left", player.name));
+                    publish_lobby(client, &lobby).await?;
+                }
+            }
+            AppEvent::Session(SessionEvent::Command { peer_id, cmd }) => {
+                match cmd {
+                    SessionCommand::Register { .. }
+                        if kicked.contains(&peer_id) =>
+                    {
+                        client.push(&DacttyloCommand::Kick(peer_id)).await?;
+                    }
+                    SessionCommand::Register { user } => {
+                        if lobby.player(&peer_id).is_none() {
+                            chat.notice(format!("{} joined", user));
+                            lobby.state.players.push(LobbyPlayer {
+                                peer_id,
+                                name: user,
+                                ready: false,
+                            });
+                        }
+                        // answers registrations sent again as well
+                        publish_lobby(client, &lobby).await?;
+                    }
+                    SessionCommand::Push(payload) => {
+                        let cmd = match deserialize(&payload) {
+                            Ok(cmd) => cmd,
+                            // such as a message from another version
+                            Err(_) => continue,
+                        };
+                        match cmd {
+                            DacttyloCommand::Ready(ready) => {
+                                if let Some(player) = lobby.player_mut(&peer_id)
+                                {
+                                    player.ready = ready;
+                                    publish_lobby(client, &lobby).await?;
+                                }
+                            }
+                            DacttyloCommand::Chat(msg)
+                                if !kicked.contains(&peer_id) =>
+                            {
+                                chat.push(msg);
+                            }
+                            _ => (),
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Lobby of a player, which follows the players listed by the host
+/// until the race starts. Only the host named in the metadata can
+/// update the lobby, kick the player or start the race. Returns the
+/// race, or nothing if the player left.
+pub async fn run_player_lobby(
+    term: &mut Terminal<CrosstermBackend<Stdout>>,
+    session: &mut SessionHandle,
+    metadata: &DacttyloMetadata,
+    username: &str,
+    keys: &KeyBindings,
+    chat: &mut Chat,
+) -> AsyncResult<Option<Lock>> {
+    let peer_id = session.peer_id.to_base58();
+    let mut lobby = Lobby::new(peer_id, false, "", metadata);
+    let host_id = &metadata.host_id;
+    let mut input_stream = EventStream::new();
+    // registrations may be published before the host is reachable
+    let mut register = interval(Duration::from_secs(1));
+    let client = &mut session.client;
+
+    loop {
+        render_lobby(term, &lobby, keys, chat)?;
+
+        let event = select! {
+            Some(event) = input_stream.next() => AppEvent::Term(event),
+            Some(event) = session.events.next() => event.into(),
+            _ = register.tick(), if lobby.player(&lobby.peer_id).is_none() => {
+                let user = username.to_owned();
+                client.publish(SessionCommand::Register { user }).await?;
+                continue;
+            }
+        };
+
+        match event {
+            AppEvent::Term(event) => {
+                let key = match event? {
+                    Event::Key(key) => key,
+                    _ => continue,
+                };
+
+                match lobby.handle_key(key, keys, chat) {
+                    LobbyAction::Send(text) => {
+                        chat.send(client, username, text).await?;
+                    }
+                    LobbyAction::ToggleReady => {
+                        if let Some(ready) = lobby.toggle_ready() {
+                            let cmd = DacttyloCommand::Ready(ready);
+                            client.push(&cmd).await?;
+                        }
+                    }
+                    LobbyAction::Quit => return Ok(None),
+                    _ => (),
+                }
+            }
+            AppEvent::Session(SessionEvent::PeerLeft { peer_id })
+                if &peer_id == host_id =>
+            {
+                return Err("the host left the session".into());
+            }
+            AppEvent::Session(SessionEvent::Command { peer_id, cmd }) => {
+                let from_host = &peer_id == host_id;
+                match cmd {
+                    SessionCommand::LockSession {
+                        registered_users,
+                        session_start,
+                    } if from_host => {
+                        let session_start: DateTime<Utc> =
+                            session_start.parse().map_err(|_| {
+                                "invalid date time for session start"
+                            })?;
+                        return Ok(Some((session_start, registered_users)));
+                    }
+                    SessionCommand::Push(payload) => {
+                        let cmd = match deserialize(&payload) {
+                            Ok(cmd) => cmd,
+                            // such as a message from another version
+                            Err(_) => continue,
+                        };
+                        match cmd {
+                            DacttyloCommand::Lobby(state) if from_host => {
+                                lobby.state = state;
+                            }
+                            DacttyloCommand::Kick(kicked)
+                                if from_host && kicked == lobby.peer_id =>
+                            {
+                                return Err(
+                                    "kicked from the session by the host"
+                                        .into(),
+                                );
+                            }
+                            DacttyloCommand::Chat(msg) => {
+                                chat.push(msg);
+                            }
+                            _ => (),
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Close the registrations and schedule the race in a few seconds
+async fn lock_registrations(
+    client: &mut SessionClient,
+    registered_users: HashMap<String, String>,
+) -> AsyncResult<DateTime<Utc>> {
+    let date = datetime_in(chrono::Duration::seconds(3)).unwrap();
+    let lock_cmd = SessionCommand::LockSession {
+        registered_users,
+        session_start: date.to_string(),
+    };
+    client.publish(lock_cmd).await?;
+
+    Ok(date)
+}
+
+async fn publish_lobby(
+    client: &mut SessionClient,
+    lobby: &Lobby,
+) -> AsyncResult<()> {
+    client
+        .push(&DacttyloCommand::Lobby(lobby.state.clone()))
+        .await
+}
+
+fn render_lobby(
+    term: &mut Terminal<CrosstermBackend<Stdout>>,
+    lobby: &Lobby,
+    keys: &KeyBindings,
+    chat: &Chat,
+) -> AsyncResult<()> {
+    term.draw(|f| {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Min(6),
+                    Constraint::Length(3),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+
+        let main_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(32), Constraint::Min(20)].as_ref())
+            .split(chunks[1]);
+
+        render_summary(f, chunks[0], lobby);
+        render_players(f, main_chunks[0], lobby);
+        render_log(f, main_chunks[1], chat, "Chat");
+        render_input(f, chunks[2], chat);
+        render_help(f, chunks[3], lobby, keys);
+
+        f.render_widget(ColorFilter::new(ColorSupport::current()), f.size());
+    })?;
+
+    Ok(())
+}
+
+fn render_summary(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    lobby: &Lobby,
+) {
+    let title = match lobby.state.host.as_str() {
+        "" => "Lobby".to_owned(),
+        host => format!("Lobby of {}", host),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ))
+        .style(Style::default().bg(Color::Reset).fg(Color::White));
+
+    let paragraph = Paragraph::new(lobby.summary.as_str())
+        .block(block)
+        .alignment(Alignment::Center);
+    f.render_widget(paragraph, area);
+}
+
+fn render_players(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    lobby: &Lobby,
+) {
+    let players = &lobby.state.players;
+    let ready = players.iter().filter(|p| p.ready).count();
+    let items: Vec<ListItem> = players
+        .iter()
+        .map(|player| {
+            let (mark, color) = match player.ready {
+                true => ("✔", Color::Green),
+                false => ("…", Color::Gray),
+            };
+            let mut name = Style::default();
+            if player.peer_id == lobby.peer_id {
+                name = name.add_modifier(Modifier::BOLD);
+            }
+            ListItem::new(Spans::from(vec![
+                Span::styled(mark, Style::default().fg(color)),
+                Span::raw(" "),
+                Span::styled(player.name.as_str(), name),
+            ]))
+        })
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Players {}/{} ready", ready, players.len()))
+        .style(Style::default().bg(Color::Reset).fg(Color::White));
+    let list = List::new(items).block(block).highlight_symbol("> ");
+
+    // only the host picks players to kick
+    let mut state = ListState::default();
+    if lobby.is_host {
+        state.select(Some(lobby.selected).filter(|&i| i < players.len()));
+    }
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_help(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    lobby: &Lobby,
+    keys: &KeyBindings,
+) {
+    let mut help = vec!["Enter: send".to_owned()];
+    if lobby.player(&lobby.peer_id).is_some() {
+        help.push(format!("{}: ready", keys.ready));
+    }
+    if lobby.is_host {
+        help.push("Up/Down: select".to_owned());
+        help.push(format!("{}: kick", keys.kick));
+        help.push(format!("{}: start", keys.start));
+    }
+    help.push(format!("{}: quit", keys.quit));
+
+    let paragraph = Paragraph::new(help.join(" | "))
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center);
+    f.render_widget(paragraph, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn lobby(names: &[&str]) -> Lobby {
+        let metadata = DacttyloMetadata {
+            file_name: "main.rs".to_owned(),
+            syntax_name: "Rust".to_owned(),
+            text: "fn main() {}\n".to_owned(),
+            skipped: vec![],
+            host_id: "al".to_owned(),
+        };
+        let mut lobby = Lobby::new("al".to_owned(), true, "al", &metadata);
+        for name in names {
+            lobby.state.players.push(LobbyPlayer {
+                peer_id: name.to_string(),
+                name: name.to_string(),
+                ready: false,
+            });
+        }
+        lobby
+    }
+
+    fn press(lobby: &mut Lobby, code: KeyCode) -> LobbyAction {
+        let key = KeyEvent::new(code, KeyModifiers::NONE);
+        lobby.handle_key(key, &KeyBindings::default(), &mut Chat::default())
+    }
+
+    #[test]
+    fn ready_check() {
+        let mut lobby = lobby(&["al", "bo"]);
+        assert!(matches!(
+            press(&mut lobby, KeyCode::Tab),
+            LobbyAction::ToggleReady
+        ));
+        assert_eq!(lobby.toggle_ready(), Some(true));
+        assert!(!lobby.can_start());
+
+        lobby.player_mut("bo").unwrap().ready = true;
+        assert!(lobby.can_start());
+        assert_eq!(lobby.toggle_ready(), Some(false));
+        assert!(!lobby.can_start());
+
+        // a referee is not in the lobby, and nobody races alone
+        let mut referee = self::lobby(&[]);
+        assert_eq!(referee.toggle_ready(), None);
+        assert!(!referee.can_start());
+    }
+
+    #[test]
+    fn kick_selection() {
+        let mut lobby = lobby(&["al", "bo", "cy"]);
+        assert_eq!(lobby.selected_opponent(), None);
+
+        press(&mut lobby, KeyCode::Down);
+        assert_eq!(lobby.selected_opponent().as_deref(), Some("bo"));
+        press(&mut lobby, KeyCode::Down);
+        press(&mut lobby, KeyCode::Down);
+        assert_eq!(lobby.selected_opponent().as_deref(), Some("cy"));
+
+        // the selection moves up when the last player leaves
+        assert_eq!(lobby.remove("cy").map(|p| p.name).as_deref(), Some("cy"));
+        assert!(lobby.remove("cy").is_none());
+        assert_eq!(lobby.selected_opponent().as_deref(), Some("bo"));
+        assert_eq!(lobby.registered_users().len(), 2);
+
+        press(&mut lobby, KeyCode::Up);
+        press(&mut lobby, KeyCode::Up);
+        assert_eq!(lobby.selected_opponent(), None);
+    }
+}
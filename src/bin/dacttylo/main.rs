@@ -1,18 +1,27 @@
 #![allow(unused)]
 
-use dacttylo::cli::Commands;
+use dacttylo::cli::{Commands, ThemesCommand};
+use dacttylo::config::Config;
+use dacttylo::utils::syntect::find_theme;
 use dacttylo::utils::types::AsyncResult;
 use host::run_host_session;
 use join::run_join_session;
 use practice::run_practice_session;
+use themes::list_themes;
+use watch::run_watch_session;
 
 mod app;
+mod chat;
 mod common;
 mod host;
 mod join;
+mod lobby;
 mod practice;
 mod protocol;
 mod report;
+mod sync;
+mod themes;
+mod watch;
 
 #[tokio::main]
 async fn main() -> AsyncResult<()> {
@@ -27,11 +36,20 @@ async fn main() -> AsyncResult<()> {
 
 async fn init_session() -> AsyncResult<()> {
     let cli = dacttylo::cli::parse();
+    let mut config = Config::load()?;
+    if let Some(theme) = cli.theme {
+        config.theme = theme;
+    }
+    if !matches!(cli.command, Commands::Themes(_)) {
+        find_theme(&config.theme)?;
+    }
 
     match cli.command {
-        Commands::Practice(opts) => run_practice_session(opts).await?,
-        Commands::Host(opts) => run_host_session(opts).await?,
-        Commands::Join(opts) => run_join_session(opts).await?,
+        Commands::Practice(opts) => run_practice_session(opts, config).await?,
+        Commands::Host(opts) => run_host_session(opts, config).await?,
+        Commands::Join(opts) => run_join_session(opts, config).await?,
+        Commands::Watch(opts) => run_watch_session(opts, config).await?,
+        Commands::Themes(ThemesCommand::List) => list_themes(&config),
     };
 
     Ok(())
@@ -1,46 +1,93 @@
 use crate::{common::*, report::*, AsyncResult};
-use crossterm::event::{Event, KeyCode, KeyEvent};
+use crossterm::event::{Event, KeyCode};
 use dacttylo::{
     app::{
         state::{PlayerPool, PlayerState},
         InputResult,
     },
     cli::{PracticeOptions, Save},
+    config::Config,
     events::AppEvent,
     game::game::Game,
-    ghost::Ghost,
-    highlighting::{Highlighter, SyntectHighlighter},
+    ghost::{Ghost, GHOST_NAME},
+    highlighting::{SyntectHighlighter, TokenFilter, TokenKinds},
     record::manager::RecordManager,
     stats::GameStats,
-    utils::tui::{enter_tui_mode, leave_tui_mode},
+    text_view::TextViewState,
+    utils::syntect::{find_theme, resolve_syntax},
+    utils::{
+        tui::{enter_tui_mode, leave_tui_mode},
+        types::StyledLine,
+    },
+};
+use std::{
+    fs::read_to_string,
+    io::Stdout,
+    time::{Duration, Instant},
 };
-use std::{fs::read_to_string, io::Stdout};
+use syntect::parsing::SyntaxReference;
 use tokio::sync::mpsc::Sender;
 use tokio_stream::StreamExt;
 use tui::{backend::CrosstermBackend, text::StyledGrapheme, Terminal};
 
-const THEME: &str = "Solarized (dark)";
-
 pub async fn run_practice_session(
     practice_opts: PracticeOptions,
+    config: Config,
 ) -> AsyncResult<()> {
-    let text = read_to_string(&practice_opts.file)?;
-    let game = Game::new(
+    let raw = read_to_string(&practice_opts.file)?;
+    let text = config.normalize.apply(&raw);
+    let syntax = resolve_syntax(
+        &practice_opts.file,
         &text,
-        if practice_opts.ghost { &["ghost"] } else { &[] },
+        practice_opts.syntax.as_deref(),
+    )?;
+    let (mut styled_lines, kinds) =
+        format_and_style(&text, syntax, &config.theme)?;
+
+    let filter = TokenFilter::new(&practice_opts.skip, &practice_opts.only);
+    let index = skip_index(&text, &kinds, filter, &config.normalize);
+    let mut record_key = config.normalize.record_key(&raw);
+    if !filter.is_empty() {
+        record_key = format!("{:?}\0{}", filter, record_key);
+    }
+
+    let mut game = Game::with_index(
+        index,
+        if practice_opts.ghost {
+            &[GHOST_NAME]
+        } else {
+            &[]
+        },
         practice_opts,
-        THEME,
+        config,
     )?;
+    dim_skipped(&mut styled_lines, game.main.index());
+
+    if game.opts.ghost {
+        let ghost = initialize_ghost(&record_key, game.client.clone())?;
+        game.ghost = Some(ghost);
+    }
 
     let mut term = enter_tui_mode(std::io::stdout())?;
-    let session_result = handle_events(&mut term, game, &text).await;
+    let result = loop {
+        let session_result =
+            handle_events(&mut term, &mut game, &record_key, &styled_lines)
+                .await;
 
-    let result = match session_result {
-        Ok(Some(session_result)) => {
-            display_session_report(&mut term, session_result).await
+        let action = match session_result {
+            Ok(Some(session_result)) => {
+                let keys = &game.config.keys;
+                display_session_report(&mut term, session_result, keys, true)
+                    .await
+            }
+            Ok(None) => Ok(ReportAction::Quit),
+            Err(e) => Err(e),
+        };
+
+        match action {
+            Ok(ReportAction::Retry) => game.restart(),
+            other => break other.map(|_| ()),
         }
-        Ok(None) => Ok(()),
-        Err(e) => Err(e),
     };
 
     leave_tui_mode(term)?;
@@ -49,57 +96,62 @@ pub async fn run_practice_session(
 
 pub fn format_and_style<'t>(
     text: &'t str,
-    file: &str,
+    syntax: &SyntaxReference,
     theme: &str,
-) -> AsyncResult<Vec<Vec<StyledGrapheme<'t>>>> {
+) -> AsyncResult<(Vec<StyledLine<'t>>, Vec<Vec<TokenKinds>>)> {
     let lines: Vec<&str> = text.split_inclusive('\n').collect();
 
     let hl = SyntectHighlighter::new()
-        .from_file((file).into())?
-        .theme(get_theme(theme))
+        .syntax(syntax)
+        .theme(find_theme(theme)?)
         .build()?;
 
-    Ok(hl.highlight(&lines))
+    Ok(hl.highlight_scoped(&lines))
 }
 
-async fn handle_events(
+async fn handle_events<'t>(
     term: &mut Terminal<CrosstermBackend<Stdout>>,
-    mut game: Game<'_, PracticeOptions>,
-    text: &str,
+    game: &mut Game<'t, PracticeOptions>,
+    record_key: &str,
+    styled_lines: &[StyledLine<'t>],
 ) -> AsyncResult<Option<SessionResult>> {
-    let styled_lines = format_and_style(text, &game.opts.file, &game.theme)?;
+    let mut view = TextViewState::new();
 
-    if game.opts.ghost {
-        let mut ghost = initialize_ghost(text, game.client.clone())?;
-        ghost.start().await?;
+    if game.opts.countdown {
+        game.start_countdown(Instant::now() + Duration::from_secs(3));
     }
+    render(term, game, styled_lines, &mut view, None)?;
 
     while let Some(event) = game.events.next().await {
-        let session_state = handle_event(event, &mut game)?;
+        let session_state = handle_event(event, game)?;
 
         if let SessionState::End(end) = session_state {
             if let SessionEnd::Finished = &end {
-                update_record_state(text, &game.main, &game.opts)?;
-                return Ok(Some(generate_session_result(game)));
+                let save = game.opts.save.or(game.config.save);
+                update_record_state(record_key, &game.main, save)?;
+                return Ok(Some(generate_session_result(game, None)));
             } else {
                 return Ok(None);
             }
         }
 
-        render(term, &game, &styled_lines)?;
+        render(term, game, styled_lines, &mut view, None)?;
     }
 
     unreachable!();
 }
 
-fn handle_event<O>(
+fn handle_event(
     event: AppEvent,
-    game: &mut Game<'_, O>,
+    game: &mut Game<'_, PracticeOptions>,
 ) -> AsyncResult<SessionState> {
     match event {
-        AppEvent::Term(e) => return Ok(handle_term(e?, &mut game.main)),
-        AppEvent::GhostInput(c) => handle_ghost_input(c, &mut game.opponents),
-        AppEvent::WpmTick => handle_wpm_tick(&mut game.stats, &game.main),
+        AppEvent::Term(e) => return handle_term(e?, game),
+        AppEvent::GhostInput(c) if game.is_started() => {
+            handle_ghost_input(c, &mut game.opponents)
+        }
+        AppEvent::WpmTick => handle_wpm_tick(game),
+        AppEvent::Tick => game.update_countdown()?,
         _ => (),
     };
 
@@ -108,12 +160,23 @@ fn handle_event<O>(
 
 fn handle_term(
     term_event: crossterm::event::Event,
-    main: &mut PlayerState<'_>,
-) -> SessionState {
+    game: &mut Game<'_, PracticeOptions>,
+) -> AsyncResult<SessionState> {
     if let Event::Key(event) = term_event {
-        let KeyEvent { code, .. } = event;
-        let c = match code {
-            KeyCode::Esc => return SessionState::End(SessionEnd::Quit),
+        let keys = game.config.keys.clone();
+        let c = match event.code {
+            _ if keys.quit.matches(&event) => {
+                return Ok(SessionState::End(SessionEnd::Quit))
+            }
+            _ if keys.pause.matches(&event) => {
+                toggle_pause(game);
+                None
+            }
+            _ if keys.restart.matches(&event) => {
+                restart(game);
+                None
+            }
+            _ if game.is_paused() || game.countdown.is_some() => None,
             KeyCode::Char(c) => Some(c),
             KeyCode::Enter => Some('\n'),
             KeyCode::Tab => Some('\t'),
@@ -121,54 +184,71 @@ fn handle_term(
         };
 
         if let Some(c) = c {
-            main.process_input(c);
-            if main.is_done() {
-                return SessionState::End(SessionEnd::Finished);
+            if !game.is_started() {
+                game.start()?;
+            }
+
+            game.main.process_input(c);
+            if game.main.is_done() {
+                return Ok(SessionState::End(SessionEnd::Finished));
             }
         }
     }
 
-    SessionState::Ongoing
+    Ok(SessionState::Ongoing)
+}
+
+fn restart(game: &mut Game<'_, PracticeOptions>) {
+    game.restart();
+    if game.opts.countdown {
+        game.start_countdown(Instant::now() + Duration::from_secs(3));
+    }
+}
+
+fn toggle_pause<O>(game: &mut Game<'_, O>) {
+    if game.is_paused() {
+        game.resume();
+    } else {
+        game.pause();
+    }
 }
 
 fn handle_ghost_input(input: InputResult, opponents: &mut PlayerPool) {
-    if let InputResult::Correct = input {
-        opponents.advance_player("ghost").unwrap();
-    }
+    opponents.replay_input(GHOST_NAME, input).unwrap();
 }
 
 pub fn initialize_ghost(
-    text: &str,
+    record_key: &str,
     client: Sender<AppEvent>,
 ) -> AsyncResult<Ghost> {
     let input_record = RecordManager::mount_dir("records")?
-        .load_from_contents(text)
+        .load_from_contents(record_key)
         .map_err(|_| "no ghost record found for this file")?;
     Ok(Ghost::new(input_record, client))
 }
 
 fn update_record_state(
-    text: &str,
+    record_key: &str,
     main: &PlayerState,
-    practice_opts: &PracticeOptions,
+    save: Option<Save>,
 ) -> AsyncResult<()> {
-    if let Some(save) = practice_opts.save {
+    if let Some(save) = save {
         let manager = RecordManager::mount_dir("records")?;
         let record = &main.recorder.record();
 
         match save {
-            Save::Override => manager.save(text, record)?,
+            Save::Override => manager.save(record_key, record)?,
 
             Save::Best => {
-                if let Ok(old_record) = manager.load_from_contents(text) {
+                if let Ok(old_record) = manager.load_from_contents(record_key) {
                     let (old_elapsed, _) = old_record.inputs.last().unwrap();
                     let (current_elapsed, _) = record.inputs.last().unwrap();
 
                     if current_elapsed.duration < old_elapsed.duration {
-                        manager.save(text, record)?;
+                        manager.save(record_key, record)?;
                     }
                 } else {
-                    manager.save(text, record)?;
+                    manager.save(record_key, record)?;
                 }
             }
         }
@@ -1,13 +1,184 @@
+use dacttylo::record::{elapsed::Elapsed, input::InputResultRecord};
 use serde::{Deserialize, Serialize};
+use std::{fmt, ops::Range, time::Duration};
 
 #[derive(Serialize, Deserialize)]
 pub enum DacttyloCommand {
-    Input(char),
+    /// Keystrokes typed since the previous frame
+    Inputs(InputFrame),
     Forfeit,
+
+    /// State of the sender, sent periodically for the other
+    /// peers to check their copy against
+    Sync(PlayerSync),
+
+    /// Ask the named player for its full state after finding
+    /// that inputs went missing
+    Resync(String),
+
+    /// Sent by the host to mark the named player, who left or stopped
+    /// typing, as not finishing the race
+    Disqualify(String),
+
+    /// State of players, sent by the host to spectators joining
+    /// a race already underway and by players answering a resync
+    Snapshot(Vec<PlayerSnapshot>),
+
+    /// Players waiting in the lobby, published by the host
+    /// whenever someone registers, leaves or gets ready
+    Lobby(LobbyState),
+
+    /// Whether the sender is ready to race
+    Ready(bool),
+
+    /// Sent by the host to turn the given peer away from the session
+    Kick(String),
+
+    /// Message typed in the chat
+    Chat(ChatMessage),
+
+    /// Quick reaction sent with a single key, even while racing
+    Emote(EmoteMessage),
+}
+
+/// Keystrokes sent together to spare the network, numbered
+/// in sending order and timestamped with the race clock of the sender
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InputFrame {
+    /// Sequence number of the first keystroke
+    pub seq: u64,
+
+    /// Time the first keystroke was typed at
+    pub start: Elapsed,
+
+    /// Keystrokes along with their delay after `start` in milliseconds
+    pub keys: Vec<(char, u16)>,
+}
+
+impl InputFrame {
+    /// Sequence number, character and time of every keystroke
+    pub fn inputs(&self) -> impl Iterator<Item = (u64, char, Duration)> + '_ {
+        let start = self.start.duration;
+        self.keys
+            .iter()
+            .zip(self.seq..)
+            .map(move |(&(ch, delay), seq)| {
+                let delay = Duration::from_millis(delay.into());
+                (seq, ch, start + delay)
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerSnapshot {
+    pub name: String,
+    pub record: InputResultRecord,
+    pub forfeited: bool,
+
+    /// Count of inputs included in the record
+    pub seq: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PlayerSync {
+    /// Count of inputs sent so far
+    pub seq: u64,
+    pub cursor: usize,
+    pub mistakes: usize,
+
+    /// Checksum of the input record
+    pub checksum: u64,
+}
+
+/// Results computed by the host and published when ending the
+/// session, so that every peer displays the same ranking
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FinalResults {
+    /// Players who finished, fastest first
+    pub ranking: Vec<PlayerResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerResult {
+    pub name: String,
+
+    /// Completion time on the clock of the player
+    pub time: Elapsed,
+    pub average_wpm: f64,
+    pub mistakes: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LobbyState {
+    /// Name of the host, who is not listed when refereeing
+    pub host: String,
+
+    /// Registered players, in registration order
+    pub players: Vec<LobbyPlayer>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LobbyPlayer {
+    pub peer_id: String,
+    pub name: String,
+    pub ready: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatMessage {
+    pub from: String,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emote {
+    Gg,
+    Fire,
+    Clap,
+    Sweat,
+}
+
+impl fmt::Display for Emote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Emote::Gg => "GG",
+            Emote::Fire => "🔥",
+            Emote::Clap => "👏",
+            Emote::Sweat => "😅",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmoteMessage {
+    pub from: String,
+    pub emote: Emote,
+}
+
+/// Emotes are shown along with the messages of the chat
+impl From<EmoteMessage> for ChatMessage {
+    fn from(msg: EmoteMessage) -> Self {
+        ChatMessage {
+            from: msg.from,
+            text: msg.emote.to_string(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct DacttyloMetadata {
+    /// Name of the file raced on, without its directory
+    pub file_name: String,
     pub syntax_name: String,
+
+    /// Text normalized by the host
     pub text: String,
+
+    /// Grapheme ranges skipped by the host, which have to be the same
+    /// for every player for the cursors to line up
+    pub skipped: Vec<Range<usize>>,
+
+    /// Peer id of the host, the only peer allowed to run the lobby
+    pub host_id: String,
 }
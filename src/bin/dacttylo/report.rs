@@ -1,12 +1,25 @@
-use std::{io::Stdout, iter, time::Duration};
+use std::{io::Stdout, iter};
 
+use crate::{
+    chat::{emote_for, emote_help, render_input, render_log, Chat},
+    protocol::{DacttyloCommand, FinalResults, PlayerResult},
+};
+use bincode::deserialize;
 use crossterm::event::Event;
 use dacttylo::{
-    game::game::Game, stats::GameStats, utils::types::AsyncResult,
-    widgets::figtext::FigTextWidget,
+    app::state::PlayerState,
+    cli::base_opts::BaseOpts,
+    config::KeyBindings,
+    events::AppEvent,
+    game::{game::Game, online_game::OnlineGame},
+    session::{event::SessionEvent, SessionCommand},
+    stats::GameStats,
+    utils::{color::ColorSupport, types::AsyncResult},
+    widgets::{color_filter::ColorFilter, figtext::FigTextWidget},
 };
 use figlet_rs::FIGfont;
 use once_cell::sync::OnceCell;
+use tokio::select;
 use tokio_stream::StreamExt;
 use tui::{
     backend::{Backend, CrosstermBackend},
@@ -25,67 +38,152 @@ pub struct SessionResult {
 }
 #[derive(Debug, Clone)]
 pub struct Ranking {
-    pub spot: usize,
-    pub names: Vec<String>,
+    /// Spot of the local player, if they finished the race
+    pub spot: Option<usize>,
+    pub players: Vec<PlayerResult>,
 }
 
-pub fn generate_session_result<O>(game: Game<'_, O>) -> SessionResult {
-    let mut ranking = game
-        .opponents
-        .players()
-        .iter()
-        .chain(iter::once((game.main.name(), &game.main)))
-        .filter_map(|(name, state)| {
-            if state.is_done() {
-                let completion_time =
-                    &state.recorder.record().inputs.last().unwrap().0;
-                Some((name.as_ref(), completion_time.duration))
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<(&str, Duration)>>();
+impl Ranking {
+    pub fn new(players: Vec<PlayerResult>, main: &str) -> Self {
+        let spot = players.iter().position(|player| player.name == main);
+        Self { spot, players }
+    }
+}
 
-    ranking.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
-    let spot = ranking
-        .iter()
-        .position(|(name, _)| name == game.main.name())
-        .unwrap();
-    let ranking = ranking
-        .into_iter()
-        .map(|(name, _)| name.to_owned())
-        .collect();
+/// What the user chose to do when leaving the report screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportAction {
+    Retry,
+    Quit,
+}
 
+/// Report of the local player, ranked with the results published
+/// by the host or with its own copy of the race otherwise
+pub fn generate_session_result<O: BaseOpts>(
+    game: &Game<'_, O>,
+    results: Option<FinalResults>,
+) -> SessionResult {
+    let results = results.unwrap_or_else(|| rank_players(game));
     SessionResult {
-        stats: game.stats,
-        ranking: Some(Ranking {
-            spot,
-            names: ranking,
-        }),
+        stats: game.stats.clone(),
+        ranking: Some(Ranking::new(results.ranking, game.main.name())),
     }
 }
 
+/// Results of the players who finished, fastest first, the local
+/// player only counts when taking part in the race
+pub fn rank_players<O: BaseOpts>(game: &Game<'_, O>) -> FinalResults {
+    let main = Some(&game.main).filter(|_| !game.opts.is_spectator());
+    rank(game.opponents.players().values().chain(main))
+}
+
+fn rank<'a, 't: 'a>(
+    players: impl Iterator<Item = &'a PlayerState<'t>>,
+) -> FinalResults {
+    let mut ranking = players
+        .filter(|state| state.is_done())
+        .filter_map(|state| {
+            let record = state.recorder.record();
+            let (time, _) = record.inputs.last()?;
+            Some(PlayerResult {
+                name: state.name().clone(),
+                time: time.clone(),
+                average_wpm: record.average_wpm(time.duration),
+                mistakes: record.count_wrong(),
+            })
+        })
+        .collect::<Vec<PlayerResult>>();
+
+    ranking.sort_by_key(|player| player.time.duration);
+    FinalResults { ranking }
+}
+
+/// Display the report until the quit key is pressed, or the restart
+/// key to retry the session when `can_retry` is set. Other keys are
+/// ignored.
 pub async fn display_session_report(
     term: &mut Terminal<CrosstermBackend<Stdout>>,
     session_result: SessionResult,
-) -> AsyncResult<()> {
-    render_report(term, &session_result).await?;
+    keys: &KeyBindings,
+    can_retry: bool,
+) -> AsyncResult<ReportAction> {
+    let help = if can_retry {
+        format!("{}: retry | {}: quit", keys.restart, keys.quit)
+    } else {
+        format!("{}: quit", keys.quit)
+    };
+    render_report(term, &session_result, &help, None).await?;
 
     let mut input_stream = crossterm::event::EventStream::new();
     while let Some(event) = input_stream.next().await {
-        let event = event?;
-        if let Event::Key(_) = event {
-            break;
+        if let Event::Key(key) = event? {
+            if can_retry && keys.restart.matches(&key) {
+                return Ok(ReportAction::Retry);
+            }
+            if keys.quit.matches(&key) {
+                return Ok(ReportAction::Quit);
+            }
         }
-        render_report(term, &session_result).await?;
+        render_report(term, &session_result, &help, None).await?;
     }
 
-    Ok(())
+    Ok(ReportAction::Quit)
+}
+
+/// Display the report of an online session along with the chat, which
+/// the players keep using until they press the quit key
+pub async fn display_online_report<O>(
+    term: &mut Terminal<CrosstermBackend<Stdout>>,
+    session_result: SessionResult,
+    app: &mut OnlineGame<'_, O>,
+    chat: &mut Chat,
+) -> AsyncResult<()> {
+    let name = app.game.main.name().clone();
+    let quit = app.game.config.keys.quit;
+    let help = format!("Enter: send | {} | {}: quit", emote_help(), quit);
+
+    loop {
+        render_report(term, &session_result, &help, Some(chat)).await?;
+
+        let event = select! {
+            Some(event) = app.game.events.next() => event,
+            Some(event) = app.session.events.next() => event.into(),
+        };
+
+        let client = &mut app.session.client;
+        match event {
+            AppEvent::Term(e) => {
+                let key = match e? {
+                    Event::Key(key) => key,
+                    _ => continue,
+                };
+
+                if quit.matches(&key) {
+                    return Ok(());
+                } else if let Some(emote) = emote_for(&key) {
+                    chat.send_emote(client, &name, emote).await?;
+                } else if let Some(text) = chat.handle_key(&key) {
+                    chat.send(client, &name, text).await?;
+                }
+            }
+            AppEvent::Session(SessionEvent::Command {
+                cmd: SessionCommand::Push(payload),
+                ..
+            }) => {
+                if let Ok(cmd) = deserialize(&payload) {
+                    chat.receive(cmd);
+                }
+            }
+            _ => (),
+        }
+    }
 }
 
 async fn render_report(
     term: &mut Terminal<CrosstermBackend<Stdout>>,
     session_result: &SessionResult,
+    help: &str,
+    chat: Option<&Chat>,
 ) -> AsyncResult<()> {
     term.draw(|f| {
         let block = Block::default().borders(Borders::ALL);
@@ -104,6 +202,7 @@ async fn render_report(
                     Constraint::Max(5),
                     Constraint::Percentage(40),
                     Constraint::Percentage(40),
+                    Constraint::Length(1),
                 ]
                 .as_ref(),
             )
@@ -111,7 +210,34 @@ async fn render_report(
 
         render_header(f, chunks[0]);
         render_data(f, chunks[1], session_result);
-        render_chart(f, chunks[2], &session_result.stats);
+        match chat {
+            Some(chat) => {
+                let chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [
+                            Constraint::Percentage(60),
+                            Constraint::Percentage(40),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(chunks[2]);
+                let chat_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(
+                        [Constraint::Min(3), Constraint::Length(3)].as_ref(),
+                    )
+                    .split(chunks[1]);
+
+                render_chart(f, chunks[0], &session_result.stats);
+                render_log(f, chat_chunks[0], chat, "Chat");
+                render_input(f, chat_chunks[1], chat);
+            }
+            None => render_chart(f, chunks[2], &session_result.stats),
+        }
+        render_help(f, chunks[3], help);
+
+        f.render_widget(ColorFilter::new(ColorSupport::current()), f.size());
     })?;
 
     Ok(())
@@ -125,6 +251,14 @@ fn render_header<B: Backend>(frame: &mut Frame<B>, area: Rect) {
     frame.render_widget(figtext, area);
 }
 
+fn render_help<B: Backend>(frame: &mut Frame<B>, area: Rect, help: &str) {
+    let paragraph = Paragraph::new(help)
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center);
+
+    frame.render_widget(paragraph, area);
+}
+
 fn render_data<B: Backend>(
     frame: &mut Frame<B>,
     area: Rect,
@@ -183,12 +317,19 @@ fn render_ranking<B: Backend>(
 ) {
     let podium = ["🥇", "🥈", "🥉"];
     let text = ranking
-        .names
+        .players
         .iter()
         .enumerate()
-        .map(|(i, name)| match podium.get(i) {
-            Some(&medal) => format!("{medal} {name}"),
-            None => format!("💩 {name}"),
+        .map(|(i, player)| {
+            let medal = podium.get(i).copied().unwrap_or("💩");
+            format!(
+                "{} {}  {:.1}s  {:.0} wpm  {} err",
+                medal,
+                player.name,
+                player.time.duration.as_secs_f64(),
+                player.average_wpm,
+                player.mistakes
+            )
         })
         .collect::<Vec<_>>()
         .join("\r\n");
@@ -276,3 +417,38 @@ fn load_header_font() -> &'static FIGfont {
         FIGfont::from_content(s).unwrap()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dacttylo::{app::state::PlayerPool, text_index::TextIndex};
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn fastest_first() {
+        let index = Arc::new(TextIndex::new("ab"));
+        let mut pool =
+            PlayerPool::new(index).with_players(&["al", "bo", "cy", "di"]);
+        let secs = Duration::from_secs;
+        for (name, done) in [("al", 9), ("bo", 4)] {
+            pool.process_input_at(name, 'a', secs(1)).unwrap();
+            pool.process_input_at(name, 'x', secs(2)).unwrap();
+            pool.process_input_at(name, 'b', secs(done)).unwrap();
+        }
+        // forfeited and unfinished players are not ranked
+        pool.process_input_at("cy", 'a', secs(1)).unwrap();
+        pool.forfeit("cy").unwrap();
+        pool.process_input_at("di", 'a', secs(1)).unwrap();
+
+        let results = rank(pool.players().values());
+        let names: Vec<&str> =
+            results.ranking.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["bo", "al"]);
+        assert_eq!(results.ranking[0].time.duration, secs(4));
+        assert_eq!(results.ranking[0].mistakes, 1);
+
+        let ranking = Ranking::new(results.ranking.clone(), "al");
+        assert_eq!(ranking.spot, Some(1));
+        assert_eq!(Ranking::new(results.ranking, "di").spot, None);
+    }
+}
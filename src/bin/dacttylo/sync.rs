@@ -0,0 +1,209 @@
+use crate::protocol::{InputFrame, PlayerSnapshot, PlayerSync};
+use dacttylo::{
+    app::state::{PlayerPool, PlayerState, PlayerStatus},
+    session::sequence::SequenceBuffer,
+};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Time window during which the inputs of the local player are
+/// gathered before being sent together
+pub const FRAME_DURATION: Duration = Duration::from_millis(40);
+
+/// Ordered delivery of the race inputs, frames the inputs of the
+/// local player and keeps the copies of the opponents in sync
+pub struct InputSync {
+    sent: u64,
+    frame: Option<InputFrame>,
+    frame_deadline: Instant,
+    replicas: HashMap<String, SequenceBuffer<(char, Duration)>>,
+}
+
+impl InputSync {
+    pub fn new() -> Self {
+        Self {
+            sent: 0,
+            frame: None,
+            frame_deadline: Instant::now(),
+            replicas: HashMap::new(),
+        }
+    }
+
+    /// Number an input of the local player and add it to the frame
+    /// being gathered
+    pub fn queue(&mut self, ch: char, elapsed: Duration) {
+        if self.frame.is_none() {
+            self.frame_deadline = Instant::now() + FRAME_DURATION;
+        }
+        let seq = self.sent;
+        let frame = self.frame.get_or_insert_with(|| InputFrame {
+            seq,
+            start: elapsed.into(),
+            keys: vec![],
+        });
+        let delay = elapsed.saturating_sub(frame.start.duration).as_millis();
+        frame.keys.push((ch, delay.min(u16::MAX.into()) as u16));
+        self.sent += 1;
+    }
+
+    /// Frame of the inputs queued since the last one was taken
+    pub fn take_frame(&mut self) -> Option<InputFrame> {
+        self.frame.take()
+    }
+
+    pub fn has_queued(&self) -> bool {
+        self.frame.is_some()
+    }
+
+    /// When the frame being gathered is due
+    pub fn frame_deadline(&self) -> Instant {
+        self.frame_deadline
+    }
+
+    /// State of the local player for the other peers to check against
+    pub fn local_sync(&self, main: &PlayerState) -> PlayerSync {
+        sync_of(main, self.sent)
+    }
+
+    /// Full state of the local player
+    pub fn local_snapshot(&self, main: &PlayerState) -> PlayerSnapshot {
+        snapshot_of(main, self.sent)
+    }
+
+    /// Full state of the copy of an opponent
+    pub fn snapshot(&self, player: &PlayerState) -> PlayerSnapshot {
+        snapshot_of(player, self.applied(player.name()))
+    }
+
+    /// Apply the inputs of an opponent in sending order with the time
+    /// they were typed at, returns whether inputs just went missing
+    pub fn receive(
+        &mut self,
+        pool: &mut PlayerPool,
+        name: &str,
+        frame: &InputFrame,
+    ) -> bool {
+        let replica = self.replica(name);
+        let had_gap = replica.has_gap();
+        for (seq, ch, elapsed) in frame.inputs() {
+            for (ch, elapsed) in replica.push(seq, (ch, elapsed)) {
+                pool.process_input_at(name, ch, elapsed).ok();
+            }
+        }
+
+        !had_gap && replica.has_gap()
+    }
+
+    /// Compare the copy of an opponent with the state it announced,
+    /// returns whether they diverged
+    pub fn diverged(
+        &self,
+        pool: &PlayerPool,
+        name: &str,
+        sync: &PlayerSync,
+    ) -> bool {
+        let player = match pool.player(name) {
+            Some(player) => player,
+            None => return false,
+        };
+
+        let applied = self.applied(name);
+        match applied.cmp(&sync.seq) {
+            // inputs sent after the sync arrived first
+            Ordering::Greater => false,
+            // trailing inputs are missing
+            Ordering::Less => true,
+            Ordering::Equal => sync != &sync_of(player, applied),
+        }
+    }
+
+    /// Replace the copy of an opponent with its full state, unless
+    /// later inputs were already applied
+    pub fn restore(
+        &mut self,
+        pool: &mut PlayerPool,
+        snapshot: &PlayerSnapshot,
+    ) {
+        let name = snapshot.name.as_str();
+        let player = match pool.player(name) {
+            Some(player) => player,
+            None => return,
+        };
+        if snapshot.seq < self.applied(name) {
+            return;
+        }
+
+        let forfeited =
+            snapshot.forfeited || player.status() == PlayerStatus::Forfeited;
+        if pool.restore(name, &snapshot.record, forfeited).is_err() {
+            return;
+        }
+        for (ch, elapsed) in self.replica(name).skip_to(snapshot.seq) {
+            pool.process_input_at(name, ch, elapsed).ok();
+        }
+    }
+
+    fn applied(&self, name: &str) -> u64 {
+        self.replicas.get(name).map_or(0, SequenceBuffer::next_seq)
+    }
+
+    fn replica(&mut self, name: &str) -> &mut SequenceBuffer<(char, Duration)> {
+        self.replicas
+            .entry(name.to_owned())
+            .or_insert_with(SequenceBuffer::new)
+    }
+}
+
+/// Whether the state of the player `name` can come from `sender`: the
+/// peer registered under this name, or the host relaying the states of
+/// every player to spectators
+pub fn vouches_for(
+    registered_users: &HashMap<String, String>,
+    host_id: Option<&str>,
+    sender: &str,
+    name: &str,
+) -> bool {
+    host_id == Some(sender)
+        || registered_users
+            .get(sender)
+            .is_some_and(|user| user == name)
+}
+
+fn sync_of(player: &PlayerState, seq: u64) -> PlayerSync {
+    let record = player.recorder.record();
+    PlayerSync {
+        seq,
+        cursor: player.cursor(),
+        mistakes: record.count_wrong(),
+        checksum: record.checksum(),
+    }
+}
+
+fn snapshot_of(player: &PlayerState, seq: u64) -> PlayerSnapshot {
+    PlayerSnapshot {
+        name: player.name().clone(),
+        record: player.recorder.record().clone(),
+        forfeited: player.status() == PlayerStatus::Forfeited,
+        seq,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_senders() {
+        let users: HashMap<String, String> =
+            [("p1".to_owned(), "al".to_owned())].into();
+
+        assert!(vouches_for(&users, None, "p1", "al"));
+        assert!(!vouches_for(&users, None, "p1", "bo"));
+        assert!(!vouches_for(&users, None, "p2", "al"));
+        assert!(vouches_for(&users, Some("h"), "h", "bo"));
+        assert!(!vouches_for(&users, Some("h"), "p2", "bo"));
+    }
+}
@@ -0,0 +1,133 @@
+use crossterm::style::{
+    Color as CColor, ResetColor, SetBackgroundColor, SetForegroundColor,
+};
+use dacttylo::{
+    config::Config,
+    utils::{
+        color::ColorSupport,
+        syntect::{find_theme, syntect_load_defaults, theme_names},
+    },
+};
+use syntect::{easy::HighlightLines, highlighting};
+use tui::style::Color;
+
+const PREVIEW: &str = "fn main() {
+    // say hello
+    let count: u32 = 42;
+    println!(\"hello {}\", count);
+}
+";
+
+/// Print every available theme along with a highlighted code sample,
+/// only the names are printed when colours are disabled
+pub fn list_themes(config: &Config) {
+    let support = ColorSupport::current();
+    let (syntax_set, _) = syntect_load_defaults();
+    let syntax = syntax_set
+        .find_syntax_by_extension("rs")
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+
+    for name in theme_names() {
+        let marker = if name == config.theme { "*" } else { " " };
+        println!("{} {}", marker, name);
+        if support == ColorSupport::NoColor {
+            continue;
+        }
+        println!();
+
+        let theme = find_theme(name).unwrap();
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        for line in PREVIEW.split_inclusive('\n') {
+            let escaped: String = highlighter
+                .highlight(line, syntax_set)
+                .into_iter()
+                .map(|(style, token)| {
+                    escape(support, style, token.trim_end_matches('\n'))
+                })
+                .collect();
+            println!("    {}{}", escaped, ResetColor);
+        }
+        println!();
+    }
+}
+
+/// Wrap the token in escape codes for its colours, reduced to what the
+/// terminal supports
+fn escape(
+    support: ColorSupport,
+    style: highlighting::Style,
+    token: &str,
+) -> String {
+    let degrade = |c: highlighting::Color| -> CColor {
+        to_crossterm(support.degrade(Color::Rgb(c.r, c.g, c.b)))
+    };
+    format!(
+        "{}{}{}",
+        SetBackgroundColor(degrade(style.background)),
+        SetForegroundColor(degrade(style.foreground)),
+        token
+    )
+}
+
+fn to_crossterm(color: Color) -> CColor {
+    match color {
+        Color::Reset => CColor::Reset,
+        Color::Black => CColor::Black,
+        Color::Red => CColor::DarkRed,
+        Color::Green => CColor::DarkGreen,
+        Color::Yellow => CColor::DarkYellow,
+        Color::Blue => CColor::DarkBlue,
+        Color::Magenta => CColor::DarkMagenta,
+        Color::Cyan => CColor::DarkCyan,
+        Color::Gray => CColor::Grey,
+        Color::DarkGray => CColor::DarkGrey,
+        Color::LightRed => CColor::Red,
+        Color::LightGreen => CColor::Green,
+        Color::LightBlue => CColor::Blue,
+        Color::LightYellow => CColor::Yellow,
+        Color::LightMagenta => CColor::Magenta,
+        Color::LightCyan => CColor::Cyan,
+        Color::White => CColor::White,
+        Color::Indexed(i) => CColor::AnsiValue(i),
+        Color::Rgb(r, g, b) => CColor::Rgb { r, g, b },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_colors_degraded() {
+        let style = highlighting::Style {
+            foreground: highlighting::Color {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            background: highlighting::Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            font_style: highlighting::FontStyle::empty(),
+        };
+
+        let true_color = escape(ColorSupport::TrueColor, style, "x");
+        assert!(true_color.contains("38;2;255;0;0"));
+
+        let ansi256 = escape(ColorSupport::Ansi256, style, "x");
+        assert!(!ansi256.contains(";2;"));
+        assert!(ansi256.contains("38;5;"));
+
+        let ansi16 = escape(ColorSupport::Ansi16, style, "x");
+        let expected = format!(
+            "{}{}x",
+            SetBackgroundColor(to_crossterm(Color::Black)),
+            SetForegroundColor(to_crossterm(Color::LightRed))
+        );
+        assert_eq!(ansi16, expected);
+    }
+}
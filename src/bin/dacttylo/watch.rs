@@ -0,0 +1,255 @@
+use crate::{
+    chat::Chat,
+    common::*,
+    host::highlight,
+    protocol::{DacttyloCommand, DacttyloMetadata},
+    sync::{vouches_for, InputSync},
+};
+use bincode::deserialize;
+use chrono::{DateTime, Utc};
+use crossterm::event::Event;
+use dacttylo::{
+    cli::WatchOptions,
+    config::Config,
+    events::AppEvent,
+    game::{game::Game, online_game::OnlineGame},
+    session::{
+        self, event::SessionEvent, session_handle::SessionHandle,
+        SessionCommand, SessionData,
+    },
+    text_index::TextIndex,
+    text_view::TextViewState,
+    utils::{
+        syntect::find_syntax,
+        time::instant_at,
+        tui::{enter_tui_mode, leave_tui_mode},
+        types::{AsyncResult, StyledLine},
+    },
+};
+use std::{
+    collections::HashMap,
+    io::Stdout,
+    time::{Duration, Instant},
+};
+use tokio::{select, time::interval};
+use tokio_stream::StreamExt;
+use tui::{backend::CrosstermBackend, Terminal};
+
+pub async fn run_watch_session(
+    opts: WatchOptions,
+    config: Config,
+) -> AsyncResult<()> {
+    let mut session = session::new().await?;
+    println!("Local peer id: {:?}", session.peer_id);
+
+    let SessionData {
+        session_id,
+        metadata,
+    } = session.client.await_session_for_host(&opts.host).await;
+    let metadata: DacttyloMetadata = deserialize(&metadata)?;
+
+    println!("Session found!");
+    session.client.join_session(session_id.clone()).await?;
+    println!("Watching session `{}`, waiting for the race...", session_id);
+
+    let host_id = metadata.host_id.as_str();
+    let (start_date, registered_users) =
+        await_lock(&mut session, host_id).await?;
+    let player_names: Vec<&str> =
+        registered_users.values().map(|n| n.as_ref()).collect();
+
+    let index =
+        TextIndex::with_skipped_ranges(&metadata.text, &metadata.skipped);
+    let theme = config.theme.clone();
+    let mut app = OnlineGame::new(
+        session,
+        Game::with_index(index, &player_names, opts, config)?,
+    );
+
+    let lines: Vec<&str> = metadata.text.split_inclusive('\n').collect();
+    if find_syntax(&metadata.syntax_name).is_err() {
+        println!(
+            "Syntax `{}` is not available, falling back to plain text",
+            metadata.syntax_name
+        );
+    }
+    let (mut lines, _) = highlight(&metadata.syntax_name, &theme, &lines)?;
+    dim_skipped(&mut lines, app.game.main.index());
+
+    // late spectators start their clocks from the actual start
+    // of the race to line up with the timestamps of the snapshot
+    let start = instant_at(start_date);
+    if start <= Instant::now() {
+        app.game.start_at(start)?;
+    } else {
+        app.game.start_countdown(start);
+    }
+
+    let mut term = enter_tui_mode(std::io::stdout())?;
+    let users = &registered_users;
+    let result =
+        handle_events(&mut term, users, host_id, &mut app, &lines).await;
+
+    leave_tui_mode(term)?;
+    result
+}
+
+/// Ask the host to announce the players and the start date, which
+/// it does again for every new spectator once locked
+async fn await_lock(
+    session: &mut SessionHandle,
+    host_id: &str,
+) -> AsyncResult<(DateTime<Utc>, HashMap<String, String>)> {
+    // the request may be published before the host is reachable
+    let mut spectate = interval(Duration::from_secs(1));
+    let client = &mut session.client;
+
+    loop {
+        let event = select! {
+            event = session.events.next() => {
+                event.ok_or("event stream closed unexpectedly")?
+            }
+            _ = spectate.tick() => {
+                client.publish(SessionCommand::Spectate).await?;
+                continue;
+            }
+        };
+
+        if let SessionEvent::Command {
+            peer_id,
+            cmd:
+                SessionCommand::LockSession {
+                    registered_users,
+                    session_start,
+                },
+        } = event
+        {
+            if peer_id != host_id {
+                continue;
+            }
+            let session_start: DateTime<Utc> = session_start
+                .parse()
+                .map_err(|_| "invalid date time for session start")?;
+            return Ok((session_start, registered_users));
+        }
+    }
+}
+
+/// Follow the race until the user quits, the final standing stays
+/// on screen once every player is done
+async fn handle_events<'t>(
+    term: &mut Terminal<CrosstermBackend<Stdout>>,
+    registered_users: &HashMap<String, String>,
+    host_id: &str,
+    app: &mut OnlineGame<'t, WatchOptions>,
+    lines: &[StyledLine<'t>],
+) -> AsyncResult<()> {
+    let mut view = TextViewState::new();
+    let mut sync = InputSync::new();
+    let mut chat = Chat::default();
+
+    loop {
+        let event = select! {
+            Some(event) = app.game.events.next() => event,
+            Some(event) = app.session.events.next() => event.into()
+        };
+
+        match event {
+            AppEvent::Term(e) => {
+                if let Event::Key(key) = e? {
+                    if app.game.config.keys.quit.matches(&key) {
+                        return Ok(());
+                    }
+                }
+            }
+            AppEvent::Session(e) => {
+                let (users, sync) = (registered_users, &mut sync);
+                handle_session_event(e, users, host_id, app, sync, &mut chat)
+                    .await?
+            }
+            AppEvent::WpmTick => handle_leader_wpm(&mut app.game),
+            AppEvent::Tick => app.game.update_countdown()?,
+            _ => (),
+        }
+
+        render(term, &app.game, lines, &mut view, Some(&chat))?;
+    }
+}
+
+async fn handle_session_event(
+    event: SessionEvent,
+    registered_users: &HashMap<String, String>,
+    host_id: &str,
+    app: &mut OnlineGame<'_, WatchOptions>,
+    sync: &mut InputSync,
+    chat: &mut Chat,
+) -> AsyncResult<()> {
+    let game = &mut app.game;
+    let client = &mut app.session.client;
+
+    let (peer_id, payload) = match event {
+        SessionEvent::Command {
+            peer_id,
+            cmd: SessionCommand::Push(payload),
+        } => (peer_id, payload),
+        SessionEvent::PeerLeft { peer_id } => {
+            if let Some(username) = registered_users.get(&peer_id) {
+                game.opponents.forfeit(username).ok();
+            }
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+
+    // the host is not registered when refereeing
+    let username = registered_users
+        .get(&peer_id)
+        .filter(|name| is_racing(&game.opponents, name));
+
+    // payloads that fail to decode, such as from another version,
+    // are skipped
+    let cmd = match deserialize(&payload) {
+        Ok(cmd) => cmd,
+        Err(_) => return Ok(()),
+    };
+    match cmd {
+        DacttyloCommand::Inputs(frame) => {
+            if let Some(username) = username {
+                if sync.receive(&mut game.opponents, username, &frame) {
+                    let cmd = DacttyloCommand::Resync(username.clone());
+                    client.push(&cmd).await?;
+                }
+            }
+        }
+        DacttyloCommand::Forfeit => {
+            if let Some(username) = username {
+                game.opponents.forfeit(username).ok();
+            }
+        }
+        DacttyloCommand::Sync(state) => {
+            if let Some(username) = username {
+                if sync.diverged(&game.opponents, username, &state) {
+                    let cmd = DacttyloCommand::Resync(username.clone());
+                    client.push(&cmd).await?;
+                }
+            }
+        }
+        DacttyloCommand::Snapshot(players) => {
+            let host_id = Some(host_id);
+            for player in &players {
+                let name = &player.name;
+                if vouches_for(registered_users, host_id, &peer_id, name) {
+                    sync.restore(&mut game.opponents, player);
+                }
+            }
+        }
+        DacttyloCommand::Disqualify(name) if peer_id == host_id => {
+            game.opponents.forfeit(&name).ok();
+        }
+        DacttyloCommand::Chat(msg) => chat.push(msg),
+        DacttyloCommand::Emote(msg) => chat.push(msg.into()),
+        _ => (),
+    }
+
+    Ok(())
+}
@@ -1,18 +1,27 @@
-use super::{HostOptions, JoinOptions, PracticeOptions};
+use super::{HostOptions, JoinOptions, PracticeOptions, WatchOptions};
 
 pub trait BaseOpts {
     fn get_username(&self) -> Option<&str>;
+
+    /// Whether the local user only watches the session
+    fn is_spectator(&self) -> bool {
+        false
+    }
 }
 
 impl BaseOpts for HostOptions {
     fn get_username(&self) -> Option<&str> {
-        Some(&self.username)
+        self.username.as_deref()
+    }
+
+    fn is_spectator(&self) -> bool {
+        self.referee
     }
 }
 
 impl BaseOpts for JoinOptions {
     fn get_username(&self) -> Option<&str> {
-        Some(&self.username)
+        self.username.as_deref()
     }
 }
 
@@ -21,3 +30,13 @@ impl BaseOpts for PracticeOptions {
         self.username.as_deref()
     }
 }
+
+impl BaseOpts for WatchOptions {
+    fn get_username(&self) -> Option<&str> {
+        None
+    }
+
+    fn is_spectator(&self) -> bool {
+        true
+    }
+}
@@ -2,6 +2,7 @@ pub mod base_opts;
 
 pub use clap::{AppSettings, Parser, Subcommand};
 use clap::{ArgEnum, Args};
+use serde::Deserialize;
 
 use self::base_opts::BaseOpts;
 
@@ -13,6 +14,10 @@ use self::base_opts::BaseOpts;
 pub struct Cli {
     #[clap(subcommand)]
     pub command: Commands,
+
+    /// Syntax highlighting theme, see `dacttylo themes list`
+    #[clap(long, global = true)]
+    pub theme: Option<String>,
 }
 
 #[derive(Subcommand, Clone, Debug)]
@@ -23,34 +28,77 @@ pub enum Commands {
     /// Join a game
     Join(JoinOptions),
 
+    /// Follow a game without taking part in it
+    Watch(WatchOptions),
+
     /// Solo practice session
     Practice(PracticeOptions),
+
+    /// Manage syntax highlighting themes
+    #[clap(subcommand)]
+    Themes(ThemesCommand),
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum ThemesCommand {
+    /// List the available themes with a preview, including the
+    /// `.tmTheme` files found in the `themes` config directory
+    List,
 }
 
 #[derive(Args, Clone, Debug)]
 pub struct HostOptions {
-    /// Your username
+    /// Your username, defaults to the `username` set in the config file
     #[clap(short, long)]
-    pub username: String,
+    pub username: Option<String>,
 
     /// Path of the file to race on
     #[clap(short, long)]
     pub file: String,
+
+    /// Syntax used for highlighting, by name or extension (e.g. `Rust`, `py`),
+    /// detected from the file otherwise
+    #[clap(long)]
+    pub syntax: Option<String>,
+
+    /// Type the tokens of these kinds automatically
+    #[clap(arg_enum, long, use_delimiter = true)]
+    pub skip: Vec<TokenKind>,
+
+    /// Type everything but the tokens of these kinds automatically
+    #[clap(arg_enum, long, use_delimiter = true, conflicts_with = "skip")]
+    pub only: Vec<TokenKind>,
+
+    /// Run the session without racing, the quit key ends the session
+    /// and publishes the results
+    #[clap(long)]
+    pub referee: bool,
+
+    /// Seconds without typing after which a player does not finish
+    /// the race, 0 to wait forever
+    #[clap(long, default_value = "60")]
+    pub idle_timeout: u64,
 }
 
 #[derive(Args, Clone, Debug)]
 pub struct JoinOptions {
-    /// Your username
+    /// Your username, defaults to the `username` set in the config file
     #[clap(short, long)]
-    pub username: String,
+    pub username: Option<String>,
 
     /// The host to join
     pub host: String,
 }
 
+#[derive(Args, Clone, Debug)]
+pub struct WatchOptions {
+    /// The host to watch
+    pub host: String,
+}
+
 #[derive(Args, Clone, Debug)]
 pub struct PracticeOptions {
-    /// Your username
+    /// Your username, defaults to the `username` set in the config file
     #[clap(short, long)]
     pub username: Option<String>,
 
@@ -58,21 +106,50 @@ pub struct PracticeOptions {
     #[clap(short, long)]
     pub file: String,
 
+    /// Syntax used for highlighting, by name or extension (e.g. `Rust`, `py`),
+    /// detected from the file otherwise
+    #[clap(long)]
+    pub syntax: Option<String>,
+
+    /// Type the tokens of these kinds automatically, e.g. `--skip comments,strings`
+    #[clap(arg_enum, long, use_delimiter = true)]
+    pub skip: Vec<TokenKind>,
+
+    /// Type everything but the tokens of these kinds automatically, e.g. `--only symbols`
+    #[clap(arg_enum, long, use_delimiter = true, conflicts_with = "skip")]
+    pub only: Vec<TokenKind>,
+
     /// Race against your past self using an input record from a previous session with this file
     #[clap(short, long)]
     pub ghost: bool,
 
-    /// Update the input record for this file after this session
+    /// Start with a 3-2-1 countdown instead of starting the timer on the first keystroke
+    #[clap(short, long)]
+    pub countdown: bool,
+
+    /// Update the input record for this file after this session, defaults to the `save` mode set in the config file
     #[clap(arg_enum, short, long)]
     pub save: Option<Save>,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ArgEnum, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum Save {
     Best,
     Override,
 }
 
+/// Token kinds known from the syntax highlighting scopes
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum TokenKind {
+    Comments,
+    Strings,
+    Docstrings,
+    Symbols,
+}
+
 pub fn parse() -> Cli {
     Cli::parse()
 }
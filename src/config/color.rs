@@ -0,0 +1,73 @@
+use serde::{de::Error, Deserialize, Deserializer};
+use tui::style::Color;
+
+/// Parses a color from its name (`"red"`, `"lightblue"`...), a 256-color
+/// palette index (`"236"`) or a hex RGB triplet (`"#1e1e1e"`)
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    let color = match s.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 && hex.is_ascii() => {
+            let channel = |i: usize| {
+                u8::from_str_radix(&hex[i..i + 2], 16)
+                    .map_err(|_| format!("invalid hex color `{}`", s))
+            };
+            Color::Rgb(channel(1)?, channel(3)?, channel(5)?)
+        }
+        index => Color::Indexed(
+            index
+                .parse()
+                .map_err(|_| format!("invalid color `{}`", s))?,
+        ),
+    };
+
+    Ok(color)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Color, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_color(&s).map_err(D::Error::custom)
+}
+
+pub fn deserialize_vec<'de, D>(deserializer: D) -> Result<Vec<Color>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| parse_color(s).map_err(D::Error::custom))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_formats() {
+        assert_eq!(parse_color("Light-Red"), Ok(Color::LightRed));
+        assert_eq!(parse_color("236"), Ok(Color::Indexed(236)));
+        assert_eq!(parse_color("#14ff00"), Ok(Color::Rgb(20, 255, 0)));
+        assert!(parse_color("#14ff0").is_err());
+        assert!(parse_color("#1é4ff").is_err());
+        assert!(parse_color("purple").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{de::Error, Deserialize, Deserializer};
+use std::fmt;
+
+/// A key combination such as `esc`, `ctrl-r` or `f5`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    pub fn ctrl(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        match (self.code, event.code) {
+            // the shift modifier is already reflected in the char case
+            (KeyCode::Char(a), KeyCode::Char(b)) => {
+                a.eq_ignore_ascii_case(&b)
+                    && self.modifiers - KeyModifiers::SHIFT
+                        == event.modifiers - KeyModifiers::SHIFT
+            }
+            (a, b) => a == b && self.modifiers == event.modifiers,
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut tokens = s.split(['-', '+']).collect::<Vec<_>>();
+        let key = tokens.pop().filter(|k| !k.is_empty());
+        let key = key.ok_or_else(|| format!("invalid key binding `{}`", s))?;
+
+        let mut modifiers = KeyModifiers::NONE;
+        for token in tokens {
+            modifiers |= match token.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("invalid key modifier `{}`", token)),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            f if f.len() > 1 && f.starts_with('f') => KeyCode::F(
+                f[1..]
+                    .parse()
+                    .map_err(|_| format!("invalid key `{}`", key))?,
+            ),
+            _ if key.chars().count() == 1 => {
+                KeyCode::Char(key.chars().next().unwrap().to_ascii_lowercase())
+            }
+            _ => return Err(format!("invalid key `{}`", key)),
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift-")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        KeyBinding::parse(&s).map_err(D::Error::custom)
+    }
+}
+
+/// Session controls
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    pub quit: KeyBinding,
+    pub restart: KeyBinding,
+    pub pause: KeyBinding,
+
+    /// Toggle whether you are ready to race in the lobby
+    pub ready: KeyBinding,
+
+    /// Start the race from the lobby, host only
+    pub start: KeyBinding,
+
+    /// Turn the selected player away from the lobby, host only
+    pub kick: KeyBinding,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            quit: KeyBinding::new(KeyCode::Esc, KeyModifiers::NONE),
+            restart: KeyBinding::ctrl('r'),
+            pause: KeyBinding::ctrl('p'),
+            ready: KeyBinding::new(KeyCode::Tab, KeyModifiers::NONE),
+            start: KeyBinding::ctrl('s'),
+            kick: KeyBinding::ctrl('k'),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bindings() {
+        assert_eq!(KeyBinding::parse("ctrl-r"), Ok(KeyBinding::ctrl('r')));
+        assert_eq!(
+            KeyBinding::parse("Esc"),
+            Ok(KeyBinding::new(KeyCode::Esc, KeyModifiers::NONE))
+        );
+        assert_eq!(
+            KeyBinding::parse("alt+f5"),
+            Ok(KeyBinding::new(KeyCode::F(5), KeyModifiers::ALT))
+        );
+        assert!(KeyBinding::parse("ctrl-").is_err());
+        assert!(KeyBinding::parse("hyper-x").is_err());
+    }
+
+    #[test]
+    fn match_event() {
+        let binding = KeyBinding::ctrl('p');
+        let event = KeyEvent::new(
+            KeyCode::Char('P'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+
+        assert!(binding.matches(&event));
+        assert_eq!(binding.to_string(), "Ctrl-P");
+    }
+}
@@ -0,0 +1,202 @@
+pub mod color;
+pub mod keys;
+
+use crate::{
+    app::widget::{DacttyloStyle, Palette},
+    cli::Save,
+    normalize::Normalization,
+    utils::types::AsyncResult,
+};
+use serde::Deserialize;
+use std::{fs, path::PathBuf, time::Duration};
+
+pub use keys::{KeyBinding, KeyBindings};
+
+/// User defaults loaded from `<config dir>/dacttylo/config.toml`,
+/// command line options take precedence over these values
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Default username for all sessions
+    pub username: Option<String>,
+
+    /// Syntect theme used to highlight the text
+    pub theme: String,
+
+    /// Default save mode for practice sessions
+    pub save: Option<Save>,
+
+    /// Width of a tab stop in columns
+    pub tab_width: usize,
+
+    /// Sampled duration for the live WPM, in seconds
+    pub wpm_window: f64,
+
+    /// Refresh interval of the live WPM, in milliseconds
+    pub tick_rate: u64,
+
+    pub keys: KeyBindings,
+    pub colors: Palette,
+    pub style: DacttyloStyle,
+    pub normalize: Normalization,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            username: None,
+            theme: "Solarized (dark)".into(),
+            save: None,
+            tab_width: 4,
+            wpm_window: 4.0,
+            tick_rate: 500,
+            keys: Default::default(),
+            colors: Default::default(),
+            style: Default::default(),
+            normalize: Default::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Directory holding the configuration file and user assets
+    pub fn dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("dacttylo"))
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        Self::dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Load the user configuration, falling back to the defaults
+    /// if there is no configuration file
+    pub fn load() -> AsyncResult<Self> {
+        match Self::path() {
+            Some(path) if path.is_file() => {
+                let content = fs::read_to_string(&path)?;
+                Self::parse(&content).map_err(|e| {
+                    format!("invalid config file `{}`: {}", path.display(), e)
+                        .into()
+                })
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    pub fn wpm_window(&self) -> Duration {
+        Duration::from_secs_f64(self.wpm_window.max(0.1))
+    }
+
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_rate.max(10))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::widget::{CursorShape, ErrorStyle, LabelStyle, ScrollMode};
+    use tui::style::Color;
+
+    #[test]
+    fn partial_config() {
+        let config = Config::parse(
+            r##"
+            username = "luis"
+            save = "best"
+            tab_width = 8
+
+            [keys]
+            quit = "ctrl-q"
+
+            [colors]
+            cursor = "#ffffff"
+            opponents = ["red", "34"]
+
+            [style]
+            cursor = "underline"
+            dim_typed = true
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(config.username.as_deref(), Some("luis"));
+        assert_eq!(config.save, Some(Save::Best));
+        assert_eq!(config.tab_width, 8);
+        assert_eq!(config.theme, Config::default().theme);
+        assert_eq!(config.keys.quit, KeyBinding::ctrl('q'));
+        assert_eq!(config.keys.pause, KeyBinding::ctrl('p'));
+        assert_eq!(config.keys.start, KeyBinding::ctrl('s'));
+        assert_eq!(config.colors.cursor, Color::Rgb(255, 255, 255));
+        assert_eq!(config.colors.opponents, [Color::Red, Color::Indexed(34)]);
+        assert_eq!(config.style.cursor, CursorShape::Underline);
+        assert_eq!(config.style.errors, ErrorStyle::Background);
+        assert!(config.style.dim_typed);
+    }
+
+    #[test]
+    fn palette_and_style_overrides() {
+        let config = Config::parse(
+            r##"
+            [colors]
+            cursor = "light-blue"
+            cursor_text = "#000000"
+            wrong = "magenta"
+            wrong_text = "black"
+            mistake = "208"
+            mistake_text = "white"
+            opponent = "dark-gray"
+            opponent_text = "gray"
+            ghost = "#323232"
+            opponents = []
+
+            [style]
+            cursor = "bar"
+            errors = "strike"
+            dim_typed = true
+            match_theme = true
+            scroll = "typewriter"
+            typewriter_row = 5
+            focus = true
+            line_numbers = true
+            minimap = true
+            labels = "name"
+            "##,
+        )
+        .unwrap();
+
+        let colors = &config.colors;
+        assert_eq!(colors.cursor, Color::LightBlue);
+        assert_eq!(colors.cursor_text, Color::Rgb(0, 0, 0));
+        assert_eq!(colors.wrong, Color::Magenta);
+        assert_eq!(colors.wrong_text, Color::Black);
+        assert_eq!(colors.mistake, Color::Indexed(208));
+        assert_eq!(colors.mistake_text, Color::White);
+        assert_eq!(colors.opponent, Color::DarkGray);
+        assert_eq!(colors.opponent_text, Color::Gray);
+        assert_eq!(colors.ghost, Color::Rgb(50, 50, 50));
+        assert!(colors.opponents.is_empty());
+
+        let style = &config.style;
+        assert_eq!(style.cursor, CursorShape::Bar);
+        assert_eq!(style.errors, ErrorStyle::Strike);
+        assert!(style.dim_typed && style.match_theme && style.focus);
+        assert_eq!(style.scroll, ScrollMode::Typewriter);
+        assert_eq!(style.typewriter_row, 5);
+        assert!(style.line_numbers && style.minimap);
+        assert_eq!(style.labels, LabelStyle::Name);
+
+        assert!(Config::parse("[style]\ncursor = \"beam\"").is_err());
+        assert!(Config::parse("[colors]\nwrong = \"#12\"").is_err());
+    }
+
+    #[test]
+    fn unknown_field() {
+        assert!(Config::parse("usrname = \"luis\"").is_err());
+        assert!(Config::parse("[keys]\nqiut = \"esc\"").is_err());
+    }
+}
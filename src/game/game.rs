@@ -1,4 +1,7 @@
-use std::time::Duration;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use tokio::sync::mpsc::Sender;
 
@@ -6,8 +9,12 @@ use crate::{
     aggregate,
     app::state::{PlayerPool, PlayerState},
     cli::base_opts::BaseOpts,
+    config::Config,
     events::{app_event, AppEvent, EventAggregator},
+    ghost::Ghost,
+    normalize::is_untypeable,
     stats::GameStats,
+    text_index::TextIndex,
     utils::types::AsyncResult,
 };
 
@@ -15,12 +22,14 @@ pub struct Game<'t, O> {
     pub main: PlayerState<'t>,
     pub opponents: PlayerPool<'t>,
     pub stats: GameStats,
+    pub ghost: Option<Ghost>,
+    pub countdown: Option<Instant>,
 
     pub client: Sender<AppEvent>,
     pub events: EventAggregator<AppEvent>,
     pub opts: O,
 
-    pub theme: String,
+    pub config: Config,
 }
 
 impl<'t, O> Game<'t, O>
@@ -31,29 +40,52 @@ where
         text: &'t str,
         opponents: &[&str],
         opts: O,
-        theme: &str,
+        config: Config,
+    ) -> AsyncResult<Game<'t, O>> {
+        let index = if config.normalize.skip_untypeable {
+            TextIndex::with_skips(text, |_, g| is_untypeable(g))
+        } else {
+            TextIndex::new(text)
+        };
+
+        Self::with_index(index, opponents, opts, config)
+    }
+
+    /// Game on a text whose skipped graphemes are already set
+    pub fn with_index(
+        index: TextIndex<'t>,
+        opponents: &[&str],
+        opts: O,
+        config: Config,
     ) -> AsyncResult<Game<'t, O>> {
-        let (client, events) = Self::configure_event_stream();
+        let (client, events) = Self::configure_event_stream(config.tick_rate());
 
-        let username = opts.get_username().unwrap_or("you");
+        let username = opts
+            .get_username()
+            .or(config.username.as_deref())
+            .unwrap_or("you");
 
-        let main = PlayerState::new(username.to_owned(), text);
-        let opponents = PlayerPool::new(text).with_players(opponents);
+        let index = Arc::new(index);
+        let main = PlayerState::new(username.to_owned(), index.clone());
+        let opponents = PlayerPool::new(index).with_players(opponents);
         let stats = GameStats::default();
 
         Ok(Game {
             main,
             opponents,
             stats,
+            ghost: None,
+            countdown: None,
             client,
             events,
             opts,
-            theme: theme.to_owned(),
+            config,
         })
     }
 
-    fn configure_event_stream() -> (Sender<AppEvent>, EventAggregator<AppEvent>)
-    {
+    fn configure_event_stream(
+        tick_rate: Duration,
+    ) -> (Sender<AppEvent>, EventAggregator<AppEvent>) {
         let (client, stream) = app_event::stream();
         let task_client = client.clone();
         tokio::spawn(async move {
@@ -61,7 +93,7 @@ where
                 if task_client.send(AppEvent::WpmTick).await.is_err() {
                     break;
                 }
-                tokio::time::sleep(Duration::from_millis(500)).await;
+                tokio::time::sleep(tick_rate).await;
             }
         });
 
@@ -69,3 +101,103 @@ where
         (client, aggregate!([stream, term_io_stream] as AppEvent))
     }
 }
+
+impl<'t, O> Game<'t, O> {
+    /// Start every clock of the session at once so that the recorders
+    /// and the ghost replay share the same origin
+    pub fn start(&mut self) -> AsyncResult<()> {
+        self.start_at(Instant::now())
+    }
+
+    /// Start the session from `origin`, which is in the past when
+    /// joining a session already underway
+    pub fn start_at(&mut self, origin: Instant) -> AsyncResult<()> {
+        self.countdown = None;
+        self.main.recorder.start_at(origin);
+        self.opponents.start_at(origin);
+        if let Some(ghost) = &mut self.ghost {
+            ghost.start()?;
+        }
+
+        Ok(())
+    }
+
+    /// Reset the session in place: player states, stats, clocks and
+    /// ghost replay are brought back to their initial state
+    pub fn restart(&mut self) {
+        self.main.reset();
+        self.opponents.reset();
+        self.stats = GameStats::default();
+        self.countdown = None;
+        if let Some(ghost) = &mut self.ghost {
+            ghost.reset();
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.main.recorder.is_started()
+    }
+
+    /// Schedule the session start at `deadline`, a `Tick` event is sent
+    /// periodically until then to refresh the countdown
+    pub fn start_countdown(&mut self, deadline: Instant) {
+        self.countdown = Some(deadline);
+
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            loop {
+                let remaining =
+                    deadline.saturating_duration_since(Instant::now());
+                tokio::time::sleep(remaining.min(Duration::from_millis(100)))
+                    .await;
+                if client.send(AppEvent::Tick).await.is_err()
+                    || remaining.is_zero()
+                {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Start the session if the countdown is over
+    pub fn update_countdown(&mut self) -> AsyncResult<()> {
+        match self.countdown {
+            Some(deadline) if deadline <= Instant::now() => self.start(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whole seconds left before the session starts, rounded up
+    pub fn countdown_remaining(&self) -> Option<u64> {
+        self.countdown.map(|deadline| {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            remaining.as_millis().div_ceil(1000) as u64
+        })
+    }
+
+    /// Freeze every clock of the session: the player recorders
+    /// and the ghost replay, if any. Nothing runs before the first
+    /// keystroke or during the countdown, so there is nothing to pause.
+    pub fn pause(&mut self) {
+        if !self.is_started() {
+            return;
+        }
+        self.main.recorder.pause();
+        self.opponents.pause();
+        if let Some(ghost) = &self.ghost {
+            ghost.pause();
+        }
+    }
+
+    pub fn resume(&mut self) {
+        self.main.recorder.resume();
+        self.opponents.resume();
+        if let Some(ghost) = &self.ghost {
+            ghost.resume();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.main.recorder.is_paused()
+    }
+}
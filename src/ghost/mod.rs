@@ -2,51 +2,167 @@ use crate::{
     app::InputResult,
     events::AppEvent,
     record::{elapsed::Elapsed, input::InputResultRecord},
+    utils::time::Stopwatch,
 };
 use std::error::Error;
-use tokio::sync::mpsc::Sender;
+use tokio::{
+    sync::{mpsc::Sender, watch},
+    task::JoinHandle,
+};
+
+/// Name of the player replaying a record among the opponents
+pub const GHOST_NAME: &str = "ghost";
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Ghost {
-    inputs: Option<InputResultRecord>,
+    inputs: InputResultRecord,
     tx: Sender<AppEvent>,
+    paused: watch::Sender<bool>,
+    replay: Option<JoinHandle<()>>,
 }
 
 impl Ghost {
     pub fn new(inputs: InputResultRecord, tx: Sender<AppEvent>) -> Self {
+        let (paused, _) = watch::channel(false);
+
         Self {
-            inputs: Some(inputs),
+            inputs,
             tx,
+            paused,
+            replay: None,
         }
     }
 
-    pub async fn start(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        if let Some(record) = self.inputs.take() {
+    pub fn start(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.replay.is_none() {
             let tx = self.tx.clone();
-            let inputs: Vec<(Elapsed, InputResult)> = record.into();
+            let paused = self.paused.subscribe();
+            let inputs: Vec<(Elapsed, InputResult)> =
+                self.inputs.clone().into();
 
-            tokio::spawn(async move {
-                Self::replay_inputs(inputs, tx).await;
-            });
+            self.replay = Some(tokio::spawn(async move {
+                Self::replay_inputs(inputs, tx, paused).await;
+            }));
         }
         Ok(())
     }
 
+    /// Stop the ongoing replay, if any, so that the next call
+    /// to `start` replays the record from the beginning
+    pub fn reset(&mut self) {
+        if let Some(replay) = self.replay.take() {
+            replay.abort();
+        }
+        self.paused.send_replace(false);
+    }
+
+    /// Suspend the ongoing replay, the time spent paused is not counted
+    /// towards the ghost's input timestamps. Does nothing before the
+    /// replay starts.
+    pub fn pause(&self) {
+        if self.replay.is_some() {
+            self.paused.send_replace(true);
+        }
+    }
+
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
     async fn replay_inputs(
         inputs: Vec<(Elapsed, InputResult)>,
         tx: Sender<AppEvent>,
+        mut paused: watch::Receiver<bool>,
     ) {
-        let start = std::time::Instant::now();
+        let mut clock = Stopwatch::new();
 
         for (elapsed, char) in inputs {
-            let now = std::time::Instant::now();
             let elapsed: std::time::Duration = elapsed.into();
-            let delta = elapsed.saturating_sub(now.duration_since(start));
 
-            tokio::time::sleep(delta).await;
+            loop {
+                if *paused.borrow() {
+                    clock.pause();
+                    if paused.changed().await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+
+                clock.resume();
+                let delta = elapsed.saturating_sub(clock.elapsed());
+
+                tokio::select! {
+                    _ = tokio::time::sleep(delta) => break,
+                    changed = paused.changed() => if changed.is_err() {
+                        return;
+                    }
+                }
+            }
+
             if tx.send(AppEvent::GhostInput(char)).await.is_err() {
                 break;
             }
         }
     }
 }
+
+impl Drop for Ghost {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::{sync::mpsc, time::timeout};
+
+    #[tokio::test]
+    async fn replay_from_the_start_after_reset() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let record = InputResultRecord {
+            inputs: vec![
+                (Duration::from_millis(10).into(), InputResult::Correct),
+                (Duration::from_secs(5).into(), InputResult::Wrong('x')),
+            ],
+        };
+        let mut ghost = Ghost::new(record, tx);
+        let first = Duration::from_millis(500);
+
+        ghost.start().unwrap();
+        let input = timeout(first, rx.recv()).await.unwrap();
+        assert!(matches!(
+            input,
+            Some(AppEvent::GhostInput(InputResult::Correct))
+        ));
+
+        ghost.reset();
+        ghost.start().unwrap();
+        let input = timeout(first, rx.recv()).await.unwrap();
+        assert!(matches!(
+            input,
+            Some(AppEvent::GhostInput(InputResult::Correct))
+        ));
+    }
+
+    #[tokio::test]
+    async fn pause_before_the_replay() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let record = InputResultRecord {
+            inputs: vec![(
+                Duration::from_millis(10).into(),
+                InputResult::Correct,
+            )],
+        };
+        let mut ghost = Ghost::new(record, tx);
+
+        ghost.pause();
+        ghost.start().unwrap();
+        let input = timeout(Duration::from_millis(500), rx.recv()).await;
+        assert!(matches!(
+            input,
+            Ok(Some(AppEvent::GhostInput(InputResult::Correct)))
+        ));
+    }
+}
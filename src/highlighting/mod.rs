@@ -1,9 +1,11 @@
 mod highlighter;
 mod noop;
+mod scope;
 mod syntect;
 
 pub use self::{
     highlighter::Highlighter,
     noop::NoOpHighlighter,
+    scope::{TokenFilter, TokenKinds},
     syntect::{SyntectHighlighter, SyntectHighlighterBuilder},
 };
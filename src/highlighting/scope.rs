@@ -0,0 +1,100 @@
+use crate::cli::TokenKind;
+use syntect::parsing::Scope;
+
+/// Kinds of token a grapheme belongs to, derived from its syntax scopes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenKinds(u8);
+
+impl TokenKinds {
+    pub const NONE: Self = Self(0);
+    pub const COMMENT: Self = Self(1);
+    pub const STRING: Self = Self(1 << 1);
+    pub const DOCSTRING: Self = Self(1 << 2);
+    pub const SYMBOL: Self = Self(1 << 3);
+
+    pub fn from_scopes(scopes: &[Scope]) -> Self {
+        scopes.iter().fold(Self::NONE, |kinds, scope| {
+            let name = scope.build_string();
+            let kind = match name.as_str() {
+                s if s.contains("documentation") || s.contains("docstring") => {
+                    Self::DOCSTRING
+                }
+                s if s.starts_with("comment") => Self::COMMENT,
+                s if s.starts_with("string") => Self::STRING,
+                s if s.starts_with("punctuation")
+                    || s.starts_with("keyword.operator") =>
+                {
+                    Self::SYMBOL
+                }
+                _ => Self::NONE,
+            };
+            kinds.union(kind)
+        })
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<TokenKind> for TokenKinds {
+    fn from(kind: TokenKind) -> Self {
+        match kind {
+            TokenKind::Comments => Self::COMMENT,
+            TokenKind::Strings => Self::STRING,
+            TokenKind::Docstrings => Self::DOCSTRING,
+            TokenKind::Symbols => Self::SYMBOL,
+        }
+    }
+}
+
+impl FromIterator<TokenKind> for TokenKinds {
+    fn from_iter<I: IntoIterator<Item = TokenKind>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::NONE, |kinds, kind| kinds.union(kind.into()))
+    }
+}
+
+/// Which tokens are typed automatically: those of the `skip` kinds,
+/// or all but those of the `only` kinds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenFilter {
+    pub skip: TokenKinds,
+    pub only: TokenKinds,
+}
+
+impl TokenFilter {
+    pub fn new(skip: &[TokenKind], only: &[TokenKind]) -> Self {
+        Self {
+            skip: skip.iter().copied().collect(),
+            only: only.iter().copied().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skip.is_empty() && self.only.is_empty()
+    }
+
+    /// Whether a grapheme is typed automatically, ASCII punctuation
+    /// counts as a symbol whatever its scope so that `--only symbols`
+    /// still works with syntaxes that do not scope every operator
+    pub fn skips(&self, grapheme: &str, kinds: TokenKinds) -> bool {
+        let kinds = match grapheme.chars().next() {
+            Some(c) if c.is_ascii_punctuation() => {
+                kinds.union(TokenKinds::SYMBOL)
+            }
+            _ => kinds,
+        };
+
+        kinds.intersects(self.skip)
+            || (!self.only.is_empty() && !kinds.intersects(self.only))
+    }
+}
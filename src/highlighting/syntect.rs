@@ -1,17 +1,28 @@
 use super::highlighter::Highlighter;
+use super::scope::TokenKinds;
 use crate::utils::syntect::{syntect_load_defaults, syntect_to_tui_style};
-use crate::utils::types::AsyncResult;
+use crate::utils::types::{AsyncResult, StyledLine};
 use std::cell::RefCell;
-use syntect::easy::HighlightLines;
-use syntect::highlighting::Theme;
-use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::easy::ScopeRegionIterator;
+use syntect::highlighting::{
+    HighlightIterator, HighlightState, Highlighter as ThemeHighlighter, Theme,
+};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
 use tui::text::StyledGrapheme;
 use unicode_segmentation::UnicodeSegmentation;
 
 /// An implementation using the syntect highlighting engine
 pub struct SyntectHighlighter<'s> {
     syntax_set: &'s SyntaxSet,
-    highlighter: RefCell<HighlightLines<'s>>,
+    highlighter: ThemeHighlighter<'s>,
+    state: RefCell<ParseLineState>,
+}
+
+/// Parsing state carried from one line to the next
+struct ParseLineState {
+    parse: ParseState,
+    highlight: HighlightState,
+    scopes: ScopeStack,
 }
 
 #[allow(clippy::new_ret_no_self)]
@@ -33,32 +44,92 @@ impl<'s> Highlighter for SyntectHighlighter<'s> {
         &self,
         line: &'txt str,
     ) -> Vec<StyledGrapheme<'txt>> {
-        let tokens = self
-            .highlighter
-            .borrow_mut()
-            .highlight(line, self.syntax_set);
+        self.highlight_line_scoped(line).0
+    }
+}
+
+impl<'s> SyntectHighlighter<'s> {
+    /// Highlights the lines along with the kinds of token each
+    /// grapheme belongs to
+    pub fn highlight_scoped<'txt>(
+        &self,
+        lines: &[&'txt str],
+    ) -> (Vec<StyledLine<'txt>>, Vec<Vec<TokenKinds>>) {
+        lines
+            .iter()
+            .map(|line| self.highlight_line_scoped(line))
+            .unzip()
+    }
 
-        let tui_tokens = tokens
+    pub fn highlight_line_scoped<'txt>(
+        &self,
+        line: &'txt str,
+    ) -> (StyledLine<'txt>, Vec<TokenKinds>) {
+        let state = &mut *self.state.borrow_mut();
+        let ops = state.parse.parse_line(line, self.syntax_set);
+
+        let tokens = HighlightIterator::new(
+            &mut state.highlight,
+            &ops,
+            line,
+            &self.highlighter,
+        )
+        .map(|(style, token)| (token, syntect_to_tui_style(style)))
+        .collect::<Vec<_>>();
+
+        let mut regions = vec![];
+        for (region, op) in ScopeRegionIterator::new(&ops, line) {
+            state.scopes.apply(op);
+            if !region.is_empty() {
+                let kinds = TokenKinds::from_scopes(state.scopes.as_slice());
+                regions.push((region, kinds));
+            }
+        }
+
+        let kinds = spans_to_graphemes(line, &regions)
             .into_iter()
-            .map(|(style, token)| (token, syntect_to_tui_style(style)))
-            .collect::<Vec<_>>();
+            .map(|(_, kinds)| kinds)
+            .collect();
 
-        tokens_to_graphemes(&tui_tokens)
+        (tokens_to_graphemes(line, &tokens), kinds)
     }
 }
 
 fn tokens_to_graphemes<'tkn>(
+    line: &'tkn str,
     tokens: &[(&'tkn str, tui::style::Style)],
 ) -> Vec<StyledGrapheme<'tkn>> {
-    tokens
-        .iter()
-        .flat_map(|(token, style)| {
-            token.graphemes(true).map(|g| StyledGrapheme {
-                symbol: g,
-                style: *style,
-            })
+    spans_to_graphemes(line, tokens)
+        .into_iter()
+        .map(|(symbol, style)| StyledGrapheme { symbol, style })
+        .collect()
+}
+
+/// Segments the whole line rather than each span, so that a grapheme
+/// cluster spanning several spans is kept whole and takes the value
+/// of the span it starts in
+fn spans_to_graphemes<'l, T: Copy + Default>(
+    line: &'l str,
+    spans: &[(&str, T)],
+) -> Vec<(&'l str, T)> {
+    let mut span_ends = spans.iter().scan(0, |end, (span, value)| {
+        *end += span.len();
+        Some((*end, *value))
+    });
+    let mut current = span_ends.next();
+
+    line.grapheme_indices(true)
+        .map(|(offset, g)| {
+            while let Some((end, _)) = current {
+                if offset < end {
+                    break;
+                }
+                current = span_ends.next();
+            }
+
+            (g, current.map(|(_, value)| value).unwrap_or_default())
         })
-        .collect::<Vec<StyledGrapheme<'tkn>>>()
+        .collect()
 }
 
 #[derive(Debug, Clone)]
@@ -98,7 +169,7 @@ impl<'a> SyntectHighlighterBuilder<'a> {
             self.syntax = syntax_set
                 .find_syntax_for_file(file.as_ref())
                 .map_err(|_| "error reading file")?
-                .ok_or("failed to find syntax")?;
+                .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
         }
 
         Ok(self)
@@ -128,6 +199,11 @@ impl<'a> SyntectHighlighterBuilder<'a> {
         Ok(self)
     }
 
+    pub fn syntax(mut self, syntax: &'a SyntaxReference) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
     pub fn theme(mut self, theme: &'a Theme) -> Self {
         self.theme = theme;
         self
@@ -136,11 +212,57 @@ impl<'a> SyntectHighlighterBuilder<'a> {
     pub fn build(self) -> AsyncResult<SyntectHighlighter<'a>> {
         let (syntax_set, _) = syntect_load_defaults();
 
-        let highlighter = HighlightLines::new(self.syntax, self.theme);
+        let highlighter = ThemeHighlighter::new(self.theme);
+        let state = ParseLineState {
+            parse: ParseState::new(self.syntax),
+            highlight: HighlightState::new(&highlighter, ScopeStack::new()),
+            scopes: ScopeStack::new(),
+        };
 
         Ok(SyntectHighlighter {
             syntax_set,
-            highlighter: RefCell::new(highlighter),
+            highlighter,
+            state: RefCell::new(state),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::style::{Color, Style};
+
+    #[test]
+    fn graphemes_across_tokens() {
+        let line = "e\u{301}x\r\n";
+        let red = Style::default().fg(Color::Red);
+        let blue = Style::default().fg(Color::Blue);
+        let tokens =
+            [(&line[..1], red), (&line[1..4], blue), (&line[4..], red)];
+
+        let graphemes = tokens_to_graphemes(line, &tokens);
+        let symbols: Vec<_> = graphemes.iter().map(|g| g.symbol).collect();
+
+        assert_eq!(symbols, ["e\u{301}", "x", "\r\n"]);
+        assert_eq!(graphemes[0].style, red);
+        assert_eq!(graphemes[1].style, blue);
+        assert_eq!(graphemes[2].style, red);
+    }
+
+    #[test]
+    fn token_kinds() {
+        let (syntax_set, _) = syntect_load_defaults();
+        let hl = SyntectHighlighter::new()
+            .syntax(syntax_set.find_syntax_by_extension("rs").unwrap())
+            .build()
+            .unwrap();
+
+        let (_, kinds) = hl.highlight_scoped(&["let s = \"a\"; // c\n"]);
+        let kinds = &kinds[0];
+
+        assert!(kinds[0].is_empty());
+        assert!(kinds[9].intersects(TokenKinds::STRING));
+        assert!(kinds[11].intersects(TokenKinds::SYMBOL));
+        assert!(kinds[16].intersects(TokenKinds::COMMENT));
+    }
+}
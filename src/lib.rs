@@ -2,16 +2,19 @@
 
 pub mod app;
 pub mod cli;
+pub mod config;
 pub mod events;
 pub mod game;
 pub mod ghost;
 pub mod highlighting;
 pub mod line;
 pub mod network;
+pub mod normalize;
 pub mod record;
 pub mod session;
 pub mod stats;
 pub mod text_coord;
+pub mod text_index;
 pub mod text_view;
 pub mod utils;
 pub mod widgets;
@@ -16,6 +16,7 @@ pub trait LineProcessor {
 }
 
 const SPACE: &str = " ";
+const ZERO_WIDTH: &str = "\u{b7}";
 
 pub struct SymbolMap {
     pub tab: StyledGrapheme<'static>,
@@ -24,6 +25,7 @@ pub struct SymbolMap {
 
 pub struct BaseLineProcessor {
     pub symbols: SymbolMap,
+    pub tab_width: usize,
 }
 
 impl Default for BaseLineProcessor {
@@ -38,6 +40,7 @@ impl Default for BaseLineProcessor {
                 tab: empty_cell.clone(),
                 nl: empty_cell,
             },
+            tab_width: 4,
         }
     }
 }
@@ -65,8 +68,9 @@ impl BaseLineProcessor {
 
         for (key_offset, gphm) in line.into_iter().enumerate() {
             let remapped_key = match gphm.symbol {
-                "\n" => self.remap_newline(gphm),
+                "\n" | "\r\n" => self.remap_newline(gphm),
                 "\t" => self.remap_tab(gphm, inline_offset),
+                s if s.width() == 0 => Self::remap_zero_width(gphm),
                 _ => vec![gphm],
             };
             let column_size: usize =
@@ -83,7 +87,7 @@ impl BaseLineProcessor {
         grapheme: StyledGrapheme<'txt>,
         inline_index: usize,
     ) -> Vec<StyledGrapheme<'txt>> {
-        let tab_width = (4 - inline_index % 4) as u8;
+        let tab_width = self.tab_width - inline_index % self.tab_width;
         let style = grapheme.style.patch(Style::default().fg(Color::Yellow));
 
         let mut tab = vec![StyledGrapheme {
@@ -91,10 +95,7 @@ impl BaseLineProcessor {
             style,
         }];
 
-        tab.extend(vec![
-            StyledGrapheme { symbol: " ", style };
-            (tab_width - 1) as usize
-        ]);
+        tab.extend(vec![StyledGrapheme { symbol: " ", style }; tab_width - 1]);
 
         tab
     }
@@ -109,38 +110,71 @@ impl BaseLineProcessor {
         }]
     }
 
+    /// Graphemes without width, such as a lone combining mark or a
+    /// zero width space, still take a cell so that the cursor shows
+    fn remap_zero_width(
+        grapheme: StyledGrapheme<'_>,
+    ) -> Vec<StyledGrapheme<'_>> {
+        vec![StyledGrapheme {
+            symbol: ZERO_WIDTH,
+            style: grapheme.style,
+        }]
+    }
+
+    /// Wraps at word boundaries, words wider than a row are broken
+    /// between graphemes
     fn wrap_line(
         graphemes: Vec<StyledGrapheme>,
         width: u16,
     ) -> Vec<Vec<StyledGrapheme>> {
+        let width = width as usize;
         let mut rows: Vec<Vec<StyledGrapheme>> = vec![];
         let mut cur_row: Vec<StyledGrapheme> = vec![];
         let mut cur_row_width = 0;
 
-        let words: Vec<String> = {
-            let s = graphemes.iter().map(|g| g.symbol).collect::<String>();
-            s.split_word_bounds().map(|x| x.to_string()).collect()
-        };
+        let line = graphemes.iter().map(|g| g.symbol).collect::<String>();
+        let mut word_ends = line
+            .split_word_bound_indices()
+            .map(|(offset, word)| offset + word.len())
+            .peekable();
+
+        let mut word: Vec<StyledGrapheme> = vec![];
+        let mut word_width = 0;
+        let mut offset = 0;
 
-        let mut gphm_iter = graphemes.into_iter();
+        for gphm in graphemes {
+            offset += gphm.symbol.len();
+            word_width += gphm.symbol.width();
+            word.push(gphm);
 
-        for word in words {
-            let word_width = word.width();
-            if word_width == 0 {
+            if word_ends.peek().is_none_or(|&end| end > offset) {
                 continue;
             }
-            if word_width + cur_row_width > width as usize {
-                rows.push(cur_row);
-                cur_row = vec![];
+            while word_ends.next_if(|&end| end <= offset).is_some() {}
+
+            // words too wide for any row start right away
+            let fits_row = word_width <= width;
+            if fits_row
+                && cur_row_width + word_width > width
+                && !cur_row.is_empty()
+            {
+                rows.push(std::mem::take(&mut cur_row));
                 cur_row_width = 0;
             }
-            let styled_word: Vec<_> = (&mut gphm_iter)
-                .take(word.graphemes(true).count())
-                .collect();
-            cur_row.extend(styled_word);
-            cur_row_width += word_width;
+
+            for gphm in word.drain(..) {
+                let gphm_width = gphm.symbol.width();
+                if cur_row_width + gphm_width > width && !cur_row.is_empty() {
+                    rows.push(std::mem::take(&mut cur_row));
+                    cur_row_width = 0;
+                }
+                cur_row.push(gphm);
+                cur_row_width += gphm_width;
+            }
+            word_width = 0;
         }
 
+        cur_row.extend(word);
         if !cur_row.is_empty() {
             rows.push(cur_row);
         }
@@ -148,3 +182,38 @@ impl BaseLineProcessor {
         rows
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(line: &str, width: u16) -> Vec<String> {
+        let mut graphemes = line.graphemes(true).map(|g| StyledGrapheme {
+            symbol: g,
+            style: Style::default(),
+        });
+
+        BaseLineProcessor::default()
+            .process_line(&mut graphemes, width, Color::Reset)
+            .into_iter()
+            .map(|row| row.iter().map(|g| g.symbol).collect())
+            .collect()
+    }
+
+    #[test]
+    fn wide_graphemes() {
+        assert_eq!("👩\u{200d}💻".width(), 2);
+        assert_eq!("🇫🇷".width(), 2);
+
+        assert_eq!(rows("日本語のテキスト", 6), ["日本語", "のテキ", "スト"]);
+        assert_eq!(
+            rows("ab 👩\u{200d}💻🇫🇷 cd", 5),
+            ["ab 👩\u{200d}💻", "🇫🇷 cd"]
+        );
+        assert_eq!(
+            rows("cafe\u{301} abcdefgh", 4),
+            ["cafe\u{301}", " abc", "defg", "h"]
+        );
+        assert_eq!(rows("\u{301}a", 4), ["\u{b7}a"]);
+    }
+}
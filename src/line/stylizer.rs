@@ -7,7 +7,23 @@ use tui::{
 const TAB_SYMBOL: &str = "\u{21e5}";
 const NL_SYMBOL: &str = "\u{23ce}";
 
-pub struct LineStylizer;
+pub struct LineStylizer {
+    tab_width: usize,
+}
+
+impl LineStylizer {
+    pub fn new(tab_width: usize) -> Self {
+        Self {
+            tab_width: tab_width.max(1),
+        }
+    }
+}
+
+impl Default for LineStylizer {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
 
 impl LineProcessor for LineStylizer {
     fn process_line<'txt>(
@@ -26,6 +42,7 @@ impl LineProcessor for LineStylizer {
                 tab: yellow(TAB_SYMBOL),
                 nl: yellow(NL_SYMBOL),
             },
+            tab_width: self.tab_width,
         };
 
         processor.process_line(line, width, default_bg)
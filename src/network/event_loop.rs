@@ -6,7 +6,7 @@ use libp2p::{
     },
     mdns::{Mdns, MdnsEvent},
     swarm::SwarmEvent,
-    NetworkBehaviour, Swarm,
+    NetworkBehaviour, PeerId, Swarm,
 };
 use std::{collections::HashMap, error::Error};
 use tokio::sync::{mpsc, oneshot};
@@ -176,10 +176,11 @@ impl EventLoop {
             // SwarmEvent::ConnectionEstablished { peer_id, .. } => {
             //     println!("Connection established with {:?}", peer_id);
             // }
-
-            // SwarmEvent::ConnectionClosed { peer_id, cause, .. } => {
-            //     println!("Connection closed with {:?} because {:?}", peer_id, cause);
-            // }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => self.notify_disconnect(peer_id).await,
             _ => {}
         }
     }
@@ -210,9 +211,9 @@ impl EventLoop {
     }
 
     async fn handle_mdns_event(&mut self, event: MdnsEvent) {
-        let behaviour = self.swarm.behaviour_mut();
         match event {
             MdnsEvent::Discovered(list) => {
+                let behaviour = self.swarm.behaviour_mut();
                 for (peer, multiaddr) in list {
                     behaviour.floodsub.add_node_to_partial_view(peer);
                     behaviour.kademlia.add_address(&peer, multiaddr);
@@ -221,15 +222,27 @@ impl EventLoop {
             }
             MdnsEvent::Expired(list) => {
                 for (peer, _multiaddr) in list {
+                    let behaviour = self.swarm.behaviour_mut();
                     if !behaviour.mdns.has_node(&peer) {
                         behaviour.floodsub.remove_node_from_partial_view(&peer);
                         // self.kademlia.remove_address(&peer, &multiaddr);
+
+                        if !self.swarm.is_connected(&peer) {
+                            self.notify_disconnect(peer).await;
+                        }
                     }
                 }
             }
         }
     }
 
+    async fn notify_disconnect(&mut self, peer: PeerId) {
+        self.event_sender
+            .send(P2PEvent::PeerDisconnected { peer })
+            .await
+            .expect("Unexpected closed P2P client receiver")
+    }
+
     #[allow(clippy::single_match)]
     async fn handle_kademlia_event(&mut self, event: KademliaEvent) {
         match event {
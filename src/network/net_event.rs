@@ -1,7 +1,5 @@
 use libp2p::{floodsub::Topic, PeerId};
 
-use crate::events::AppEvent;
-
 #[derive(Clone, Debug)]
 pub enum P2PEvent {
     TopicMessage {
@@ -9,10 +7,8 @@ pub enum P2PEvent {
         topics: Vec<Topic>,
         data: Vec<u8>,
     },
-}
 
-impl From<P2PEvent> for AppEvent {
-    fn from(e: P2PEvent) -> Self {
-        AppEvent::Session(e.into())
-    }
+    /// The peer can no longer be reached, either because its last
+    /// connection closed or because it vanished from the local network
+    PeerDisconnected { peer: PeerId },
 }
@@ -0,0 +1,182 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Rewrites a text before a session so that it can be typed on a
+/// regular keyboard, the result is what players, highlighters and
+/// records all work with
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Normalization {
+    /// Replace CRLF line endings with LF
+    pub crlf: bool,
+
+    /// Apply the built-in substitutions of typographic characters,
+    /// such as smart quotes, dashes and non-breaking spaces
+    pub builtin: bool,
+
+    /// Additional substitutions, applied before the built-in ones
+    pub substitutions: BTreeMap<String, String>,
+
+    /// Type the remaining untypeable characters automatically
+    pub skip_untypeable: bool,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            crlf: true,
+            builtin: true,
+            substitutions: Default::default(),
+            skip_untypeable: true,
+        }
+    }
+}
+
+impl Normalization {
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = if self.crlf {
+            text.replace("\r\n", "\n")
+        } else {
+            text.to_owned()
+        };
+
+        for (from, to) in &self.substitutions {
+            text = text.replace(from.as_str(), to);
+        }
+
+        if self.builtin {
+            text = text.chars().fold(
+                String::with_capacity(text.len()),
+                |mut s, c| {
+                    match substitute(c) {
+                        Some(sub) => s.push_str(sub),
+                        None => s.push(c),
+                    }
+                    s
+                },
+            );
+        }
+
+        text
+    }
+
+    /// Key under which the records of a text are stored, given as read
+    /// from the file. Texts left untouched keep the raw text as key, like
+    /// the records made before normalization. Others map to a key per
+    /// settings since their inputs may not line up with the text.
+    pub fn record_key(&self, raw: &str) -> String {
+        let text = self.apply(raw);
+        let skips =
+            self.skip_untypeable && text.graphemes(true).any(is_untypeable);
+        if text == raw && !skips {
+            return text;
+        }
+
+        format!(
+            "{:?}\0{}",
+            (
+                self.crlf,
+                self.builtin,
+                &self.substitutions,
+                self.skip_untypeable
+            ),
+            text
+        )
+    }
+}
+
+fn substitute(c: char) -> Option<&'static str> {
+    let sub = match c {
+        '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{2032}' => "'",
+        '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{2033}' => "\"",
+        '\u{00ab}' => "<<",
+        '\u{00bb}' => ">>",
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => "-",
+        '\u{2026}' => "...",
+        '\u{00a0}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' => " ",
+        '\u{2190}' => "<-",
+        '\u{2192}' => "->",
+        '\u{21d0}' => "<=",
+        '\u{21d2}' => "=>",
+        '\u{2264}' => "<=",
+        '\u{2265}' => ">=",
+        '\u{2260}' => "!=",
+        '\u{00d7}' => "x",
+        '\u{2022}' => "*",
+        _ => return None,
+    };
+
+    Some(sub)
+}
+
+/// Graphemes that a regular keyboard cannot produce: control and
+/// invisible characters, arrows, box-drawing, symbols and emoji
+pub fn is_untypeable(grapheme: &str) -> bool {
+    let c = match grapheme.chars().next() {
+        Some(c) => c,
+        None => return true,
+    };
+
+    match c {
+        '\n' | '\t' => false,
+        '\r' if grapheme == "\r\n" => false,
+        _ if c.is_control() => true,
+        '\u{00ad}'
+        | '\u{200b}'..='\u{200f}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{feff}'
+        | '\u{2190}'..='\u{21ff}'
+        | '\u{2500}'..='\u{27bf}'
+        | '\u{2b00}'..='\u{2bff}'
+        | '\u{1f000}'..='\u{1faff}' => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutions() {
+        let mut norm = Normalization::default();
+        let text = "\u{201c}a\u{201d} \u{2014} b\u{2026}\r\nx\u{a0}\u{2192} y";
+        assert_eq!(norm.apply(text), "\"a\" - b...\nx -> y");
+
+        norm.substitutions.insert("\u{2014}".into(), "--".into());
+        norm.crlf = false;
+        assert_eq!(norm.apply("a\u{2014}b\r\n"), "a--b\r\n");
+
+        norm.builtin = false;
+        assert_eq!(norm.apply("\u{2026}"), "\u{2026}");
+    }
+
+    #[test]
+    fn record_keys() {
+        let mut norm = Normalization::default();
+        assert_eq!(norm.record_key("a\n"), "a\n");
+        assert_ne!(norm.record_key("a\r\n"), "a\r\n");
+        assert_ne!(norm.record_key("a \u{2502}"), "a \u{2502}");
+
+        norm.builtin = false;
+        assert_eq!(norm.record_key("a\n"), "a\n");
+        assert_ne!(
+            norm.record_key("a\u{2026}"),
+            Normalization::default().record_key("a\u{2026}")
+        );
+    }
+
+    #[test]
+    fn untypeable() {
+        assert!(is_untypeable("\r"));
+        assert!(!is_untypeable("\r\n"));
+        assert!(is_untypeable("\u{2502}"));
+        assert!(is_untypeable("\u{1f44d}\u{1f3fd}"));
+        assert!(is_untypeable("\u{200b}"));
+        assert!(!is_untypeable("\t"));
+        assert!(!is_untypeable("é"));
+        assert!(!is_untypeable("日"));
+        assert!(!is_untypeable("~"));
+    }
+}
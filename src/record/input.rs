@@ -55,7 +55,7 @@ impl InputResultRecord {
     }
 
     pub fn top_wpm(&self, sampled_size: Duration, step: Duration) -> f64 {
-        let last_ipr = self.inputs.iter().rev().next();
+        let last_ipr = self.inputs.last();
 
         match last_ipr {
             Some((elapsed, _)) => {
@@ -104,6 +104,20 @@ impl InputResultRecord {
     pub fn precision(&self) -> f64 {
         self.count_correct() as f64 / self.inputs.len() as f64
     }
+
+    /// FNV-1a hash of the inputs regardless of their timestamps,
+    /// to compare records kept on different machines
+    pub fn checksum(&self) -> u64 {
+        self.inputs
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, (_, input)| {
+                let value = match input {
+                    InputResult::Correct => 0,
+                    InputResult::Wrong(ch) => *ch as u64 + 1,
+                };
+                (hash ^ value).wrapping_mul(0x100_0000_01b3)
+            })
+    }
 }
 
 impl From<Vec<(Elapsed, InputResult)>> for InputResultRecord {
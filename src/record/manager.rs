@@ -39,6 +39,7 @@ impl<'dir> RecordManager<'dir> {
         let mut file = std::fs::OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(filepath)?;
 
         let serial = bincode::serialize(&record)?;
@@ -1,17 +1,18 @@
 use super::input::InputResultRecord;
-use crate::app::InputResult;
+use crate::{app::InputResult, utils::time::Stopwatch};
 use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub struct InputResultRecorder {
-    start: Instant,
+    clock: Stopwatch,
     record: InputResultRecord,
 }
 
 impl InputResultRecorder {
+    /// The recorder clock stays idle until `start` is called
     pub fn new() -> Self {
         Self {
-            start: Instant::now(),
+            clock: Stopwatch::idle(),
             record: InputResultRecord {
                 inputs: Default::default(),
             },
@@ -23,11 +24,48 @@ impl InputResultRecorder {
         self.record.inputs.push((elapsed.into(), input));
     }
 
+    /// Push an input timestamped beforehand, such as one replayed
+    /// from another player's record
+    pub fn push_at(&mut self, elapsed: Duration, input: InputResult) {
+        self.record.inputs.push((elapsed.into(), input));
+    }
+
+    /// Drop the inputs recorded so far, the clock keeps running
+    pub fn clear(&mut self) {
+        self.record.inputs.clear();
+    }
+
     pub fn record(&self) -> &InputResultRecord {
         &self.record
     }
 
     pub fn elapsed(&self) -> Duration {
-        Instant::now().duration_since(self.start)
+        self.clock.elapsed()
+    }
+
+    pub fn start(&mut self) {
+        self.clock.start();
+    }
+
+    pub fn start_at(&mut self, origin: Instant) {
+        self.clock.start_at(origin);
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.clock.is_started()
+    }
+
+    /// Freeze the recorder clock, inputs pushed while paused
+    /// are timestamped with the frozen elapsed time
+    pub fn pause(&mut self) {
+        self.clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.clock.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.clock.is_paused()
     }
 }
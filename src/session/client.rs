@@ -3,6 +3,7 @@ use std::{error::Error, time::Duration};
 use bincode::deserialize;
 use libp2p::{floodsub::Topic, kad::record::Key};
 use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
 
 use crate::network::P2PClient;
 use crate::session::{SessionCommand, SessionData};
@@ -152,11 +153,20 @@ impl SessionClient {
         &mut self,
         session_cmd: SessionCommand,
     ) -> AsyncResult<()> {
+        self.send(session_cmd.encode()?).await
+    }
+
+    /// Publish an application payload, same as a `SessionCommand::Push`
+    /// without serializing the payload beforehand
+    pub async fn push<T: Serialize>(&mut self, payload: &T) -> AsyncResult<()> {
+        self.send(SessionCommand::encode_push(payload)?).await
+    }
+
+    async fn send(&mut self, data: Vec<u8>) -> AsyncResult<()> {
         let current_session_id = self.get_session()?;
-        let payload = bincode::serialize(&session_cmd)?;
 
         self.p2p_client
-            .publish(Topic::new(current_session_id), payload)
+            .publish(Topic::new(current_session_id), data)
             .await
             .expect("P2P client channel failure");
 
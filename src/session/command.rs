@@ -1,6 +1,13 @@
+use crate::utils::types::AsyncResult;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// First byte of a message carrying a session command
+const CONTROL_TAG: u8 = 0;
+
+/// First byte of a message carrying a push payload as is
+const PUSH_TAG: u8 = 1;
+
 /// Communication protocol for joining, starting,
 /// and stopping sessions.
 #[derive(PartialEq, Eq, Serialize, Deserialize, Debug, Clone)]
@@ -12,6 +19,12 @@ pub enum SessionCommand {
     /// session.
     Register { user: String },
 
+    /// Ask to follow the session without taking part in it.
+    /// The host answers with the `LockSession` command once
+    /// the session is locked, along with the state of the race
+    /// if it already started.
+    Spectate,
+
     /// Command issued by the session host to communicate
     /// that registrations are closed and the session is
     /// scheduled to start at `session_start`
@@ -29,6 +42,68 @@ pub enum SessionCommand {
     Push(Vec<u8>),
 
     /// Command issued by the session host to communicate
-    /// the end of the session
-    EndSession,
+    /// the end of the session, along with application specific
+    /// results that every peer should display
+    EndSession(Vec<u8>),
+}
+
+impl SessionCommand {
+    /// Message sent over the network, push payloads are written as is
+    /// rather than serialized a second time
+    pub fn encode(&self) -> AsyncResult<Vec<u8>> {
+        match self {
+            SessionCommand::Push(payload) => {
+                let mut data = Vec::with_capacity(payload.len() + 1);
+                data.push(PUSH_TAG);
+                data.extend_from_slice(payload);
+                Ok(data)
+            }
+            cmd => {
+                let mut data = vec![CONTROL_TAG];
+                bincode::serialize_into(&mut data, cmd)?;
+                Ok(data)
+            }
+        }
+    }
+
+    /// Message of a push payload serialized straight from its value
+    pub fn encode_push<T: Serialize>(payload: &T) -> AsyncResult<Vec<u8>> {
+        let mut data = vec![PUSH_TAG];
+        bincode::serialize_into(&mut data, payload)?;
+        Ok(data)
+    }
+
+    pub fn decode(mut data: Vec<u8>) -> AsyncResult<Self> {
+        match data.first() {
+            Some(&PUSH_TAG) => {
+                data.remove(0);
+                Ok(SessionCommand::Push(data))
+            }
+            Some(&CONTROL_TAG) => Ok(bincode::deserialize(&data[1..])?),
+            _ => Err("unknown session message".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_payload_sent_as_is() {
+        let payload = bincode::serialize(&('a', 42u64)).unwrap();
+        let data = SessionCommand::encode_push(&('a', 42u64)).unwrap();
+        assert_eq!(data[1..], payload);
+
+        let cmd = SessionCommand::Push(payload.clone());
+        assert_eq!(cmd.encode().unwrap(), data);
+        assert_eq!(SessionCommand::decode(data).unwrap(), cmd);
+
+        let lock = SessionCommand::LockSession {
+            registered_users: HashMap::from([("id".into(), "zoe".into())]),
+            session_start: "now".into(),
+        };
+        let data = lock.encode().unwrap();
+        assert_eq!(SessionCommand::decode(data).unwrap(), lock);
+    }
 }
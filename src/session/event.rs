@@ -1,21 +1,35 @@
 use super::SessionCommand;
 use crate::{events::AppEvent, network::P2PEvent};
-use bincode::deserialize;
+use std::error::Error;
 
 #[derive(Debug, Clone)]
-pub struct SessionEvent {
-    pub peer_id: String,
-    pub cmd: SessionCommand,
+pub enum SessionEvent {
+    /// Command published on the session topic by a peer
+    Command {
+        peer_id: String,
+        cmd: SessionCommand,
+    },
+
+    /// A peer can no longer be reached
+    PeerLeft { peer_id: String },
 }
 
-impl From<P2PEvent> for SessionEvent {
-    fn from(e: P2PEvent) -> Self {
-        let P2PEvent::TopicMessage { source, data, .. } = e;
+impl TryFrom<P2PEvent> for SessionEvent {
+    type Error = Box<dyn Error + Send + Sync>;
 
-        SessionEvent {
-            peer_id: source.to_base58(),
-            cmd: deserialize::<SessionCommand>(&data).unwrap(),
-        }
+    fn try_from(e: P2PEvent) -> Result<Self, Self::Error> {
+        let event = match e {
+            P2PEvent::TopicMessage { source, data, .. } => {
+                SessionEvent::Command {
+                    peer_id: source.to_base58(),
+                    cmd: SessionCommand::decode(data)?,
+                }
+            }
+            P2PEvent::PeerDisconnected { peer } => SessionEvent::PeerLeft {
+                peer_id: peer.to_base58(),
+            },
+        };
+        Ok(event)
     }
 }
 
@@ -2,9 +2,10 @@ pub mod client;
 pub mod command;
 pub mod data;
 pub mod event;
+pub mod sequence;
 pub mod session_handle;
 
-use self::session_handle::SessionHandle;
+use self::session_handle::{SessionEvents, SessionHandle};
 pub use self::{
     client::SessionClient, command::SessionCommand, data::SessionData,
 };
@@ -26,7 +27,7 @@ pub async fn new() -> AsyncResult<SessionHandle> {
 
     Ok(SessionHandle {
         client: SessionClient::new(client),
-        events,
+        events: SessionEvents::new(events),
         peer_id,
     })
 }
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+/// Hands over the messages of a sender in the order they were sent,
+/// holding back those that arrive ahead of a missing one
+#[derive(Debug, Clone)]
+pub struct SequenceBuffer<T> {
+    next: u64,
+    pending: BTreeMap<u64, T>,
+}
+
+impl<T> SequenceBuffer<T> {
+    pub fn new() -> Self {
+        Self {
+            next: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Sequence number of the next message to hand over, which is
+    /// also the count of messages handed over so far
+    pub fn next_seq(&self) -> u64 {
+        self.next
+    }
+
+    /// Whether messages are held back behind a missing one
+    pub fn has_gap(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Accept a message and return the ones now in order,
+    /// duplicates and messages already handed over are dropped
+    pub fn push(&mut self, seq: u64, msg: T) -> Vec<T> {
+        if seq >= self.next {
+            self.pending.entry(seq).or_insert(msg);
+        }
+        self.drain()
+    }
+
+    /// Drop the messages before `next`, whose effects were restored
+    /// by other means, and return the held back ones now in order
+    pub fn skip_to(&mut self, next: u64) -> Vec<T> {
+        self.next = self.next.max(next);
+        self.pending = self.pending.split_off(&self.next);
+        self.drain()
+    }
+
+    fn drain(&mut self) -> Vec<T> {
+        let mut ready = vec![];
+        while let Some(msg) = self.pending.remove(&self.next) {
+            ready.push(msg);
+            self.next += 1;
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reordered_messages() {
+        let mut buffer = SequenceBuffer::new();
+
+        assert_eq!(buffer.push(0, 'a'), ['a']);
+        assert!(buffer.push(2, 'c').is_empty());
+        assert!(buffer.has_gap());
+        assert!(buffer.push(2, 'c').is_empty());
+        assert_eq!(buffer.push(1, 'b'), ['b', 'c']);
+        assert!(!buffer.has_gap());
+        assert!(buffer.push(1, 'b').is_empty());
+        assert_eq!(buffer.next_seq(), 3);
+    }
+
+    #[test]
+    fn skip_restored_messages() {
+        let mut buffer = SequenceBuffer::new();
+
+        buffer.push(3, 'd');
+        buffer.push(5, 'f');
+        assert_eq!(buffer.skip_to(3), ['d']);
+        assert!(buffer.has_gap());
+        assert!(buffer.skip_to(2).is_empty());
+        assert_eq!(buffer.skip_to(5), ['f']);
+        assert_eq!(buffer.next_seq(), 6);
+    }
+}
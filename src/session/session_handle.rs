@@ -1,11 +1,82 @@
-use super::SessionClient;
+use super::{event::SessionEvent, SessionClient};
 use crate::network::P2PEvent;
 use futures::Stream;
 use libp2p::PeerId;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
 use tokio_stream::wrappers::ReceiverStream;
 
 pub struct SessionHandle {
     pub client: SessionClient,
-    pub events: ReceiverStream<P2PEvent>,
+    pub events: SessionEvents,
     pub peer_id: PeerId,
 }
+
+/// Events of the session, messages that fail to decode are dropped
+/// so that a single bad peer cannot bring the others down
+pub struct SessionEvents {
+    p2p_events: ReceiverStream<P2PEvent>,
+}
+
+impl SessionEvents {
+    pub fn new(p2p_events: ReceiverStream<P2PEvent>) -> Self {
+        Self { p2p_events }
+    }
+}
+
+impl Stream for SessionEvents {
+    type Item = SessionEvent;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            match Pin::new(&mut self.p2p_events).poll_next(cx) {
+                Poll::Ready(Some(event)) => {
+                    if let Ok(event) = SessionEvent::try_from(event) {
+                        return Poll::Ready(Some(event));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionCommand;
+    use libp2p::floodsub::Topic;
+    use tokio::sync::mpsc;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn undecodable_messages_dropped() {
+        let (tx, rx) = mpsc::channel(4);
+        let source = PeerId::random();
+        let message = |data| P2PEvent::TopicMessage {
+            source,
+            topics: vec![Topic::new("session")],
+            data,
+        };
+        let push = SessionCommand::Push(vec![1, 2]);
+
+        tx.send(message(vec![0xff, 0])).await.unwrap();
+        tx.send(message(vec![])).await.unwrap();
+        tx.send(message(push.encode().unwrap())).await.unwrap();
+        drop(tx);
+
+        let events = SessionEvents::new(ReceiverStream::new(rx));
+        let events: Vec<SessionEvent> = events.collect().await;
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            SessionEvent::Command { cmd, .. } if cmd == &push
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::text_coord::TextCoord;
+use std::ops::Range;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Grapheme clusters of a text along with the position of each line
+/// start, computed once and shared by every player of a session
+#[derive(Debug, Clone)]
+pub struct TextIndex<'txt> {
+    text: &'txt str,
+    graphemes: Vec<&'txt str>,
+
+    /// Grapheme position of the first grapheme of each line
+    line_starts: Vec<usize>,
+
+    /// Graphemes typed automatically, empty when none are
+    skipped: Vec<bool>,
+}
+
+impl<'txt> TextIndex<'txt> {
+    /// Lines are split the same way as the highlighters do,
+    /// newlines included
+    pub fn new(text: &'txt str) -> Self {
+        let mut graphemes = vec![];
+        let mut line_starts = vec![];
+
+        for line in text.split_inclusive('\n') {
+            line_starts.push(graphemes.len());
+            graphemes.extend(line.graphemes(true));
+        }
+
+        Self {
+            text,
+            graphemes,
+            line_starts,
+            skipped: vec![],
+        }
+    }
+
+    /// Index whose graphemes matching `skip`, given their position
+    /// and symbol, are typed automatically
+    pub fn with_skips(
+        text: &'txt str,
+        mut skip: impl FnMut(usize, &str) -> bool,
+    ) -> Self {
+        let mut index = Self::new(text);
+        index.skipped = index
+            .graphemes
+            .iter()
+            .enumerate()
+            .map(|(pos, g)| skip(pos, g))
+            .collect();
+        index
+    }
+
+    /// Index skipping the graphemes within sorted position ranges
+    pub fn with_skipped_ranges(
+        text: &'txt str,
+        ranges: &[Range<usize>],
+    ) -> Self {
+        let mut ranges = ranges.iter().peekable();
+        Self::with_skips(text, |pos, _| {
+            while ranges.next_if(|range| range.end <= pos).is_some() {}
+            ranges.peek().is_some_and(|range| range.contains(&pos))
+        })
+    }
+
+    /// Sorted position ranges of the skipped graphemes
+    pub fn skipped_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = vec![];
+
+        for pos in (0..self.len()).filter(|&pos| self.is_skipped(pos)) {
+            match ranges.last_mut() {
+                Some(range) if range.end == pos => range.end += 1,
+                _ => ranges.push(pos..pos + 1),
+            }
+        }
+
+        ranges
+    }
+
+    pub fn is_skipped(&self, pos: usize) -> bool {
+        self.skipped.get(pos).copied().unwrap_or(false)
+    }
+
+    /// First position from `pos` onwards that has to be typed,
+    /// or the end of the text
+    pub fn next_typeable(&self, mut pos: usize) -> usize {
+        while pos < self.len() && self.is_skipped(pos) {
+            pos += 1;
+        }
+        pos
+    }
+
+    pub fn text(&self) -> &'txt str {
+        self.text
+    }
+
+    /// Number of graphemes in the text
+    pub fn len(&self) -> usize {
+        self.graphemes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graphemes.is_empty()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    pub fn grapheme(&self, pos: usize) -> Option<&'txt str> {
+        self.graphemes.get(pos).copied()
+    }
+
+    /// Character expected from the player to type a grapheme: its first
+    /// character once composed, so that `e` followed by a combining
+    /// accent is typed as `é` and an emoji sequence by its first emoji
+    pub fn expected(&self, pos: usize) -> Option<char> {
+        match self.grapheme(pos)? {
+            "\r\n" => Some('\n'),
+            g => g.nfc().next(),
+        }
+    }
+
+    /// Line number and grapheme index within that line of a position
+    pub fn coord(&self, pos: usize) -> Option<TextCoord> {
+        if pos >= self.len() {
+            return None;
+        }
+
+        let ln = match self.line_starts.binary_search(&pos) {
+            Ok(ln) => ln,
+            Err(ln) => ln - 1,
+        };
+
+        Some(TextCoord::new(ln, pos - self.line_starts[ln]))
+    }
+
+    /// Position of the first grapheme of a line
+    pub fn line_start(&self, ln: usize) -> Option<usize> {
+        self.line_starts.get(ln).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coords() {
+        let index = TextIndex::new("ab\n\ncd\r\nef");
+
+        assert_eq!(index.len(), 9);
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.coord(0), Some(TextCoord::new(0, 0)));
+        assert_eq!(index.coord(2), Some(TextCoord::new(0, 2)));
+        assert_eq!(index.coord(3), Some(TextCoord::new(1, 0)));
+        assert_eq!(index.coord(6), Some(TextCoord::new(2, 2)));
+        assert_eq!(index.grapheme(6), Some("\r\n"));
+        assert_eq!(index.coord(8), Some(TextCoord::new(3, 1)));
+        assert_eq!(index.coord(9), None);
+    }
+
+    #[test]
+    fn skips() {
+        let text = "a\u{2502}\u{2502}b\u{2502}";
+        let index = TextIndex::with_skips(text, |_, g| g == "\u{2502}");
+
+        assert_eq!(index.next_typeable(0), 0);
+        assert_eq!(index.next_typeable(1), 3);
+        assert_eq!(index.next_typeable(4), 5);
+
+        let ranges = index.skipped_ranges();
+        assert_eq!(ranges, [1..3, 4..5]);
+        let index = TextIndex::with_skipped_ranges(text, &ranges);
+        assert_eq!(index.skipped_ranges(), ranges);
+    }
+
+    #[test]
+    fn multilingual() {
+        let text = "cafe\u{301} 日本\n👩\u{200d}💻 🇫🇷!";
+        let index = TextIndex::new(text);
+
+        assert_eq!(index.len(), 12);
+        assert_eq!(index.grapheme(3), Some("e\u{301}"));
+        assert_eq!(index.expected(3), Some('é'));
+        assert_eq!(index.expected(5), Some('日'));
+        assert_eq!(index.coord(8), Some(TextCoord::new(1, 0)));
+        assert_eq!(index.expected(8), Some('👩'));
+        assert_eq!(index.grapheme(10), Some("🇫🇷"));
+        assert_eq!(index.coord(11), Some(TextCoord::new(1, 3)));
+    }
+}
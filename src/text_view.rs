@@ -1,24 +1,172 @@
 use crate::line::processor::LineProcessor;
 use crate::line::stylizer::LineStylizer;
+use crate::text_coord::TextCoord;
 use crate::utils::types::StyledLine;
 use std::cmp::min;
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter;
+use std::ops::{Range, RangeInclusive};
 use tui::style::{Color, Style};
 use tui::{
     buffer::Buffer,
     layout::Rect,
     text::StyledGrapheme,
-    widgets::{Block, Widget},
+    widgets::{Block, StatefulWidget, Widget},
 };
 use unicode_width::UnicodeWidthStr;
 
+type Rows<'a> = Vec<Vec<StyledGrapheme<'a>>>;
+
+/// Row as displayed, along with the line it belongs to
+struct ViewRow<'a> {
+    /// Line number and index of the row within the line, none for
+    /// the blank rows padding the view
+    ln: Option<(usize, usize)>,
+    graphemes: Vec<StyledGrapheme<'a>>,
+}
+
+impl<'a> ViewRow<'a> {
+    fn padding() -> Self {
+        Self {
+            ln: None,
+            graphemes: vec![],
+        }
+    }
+
+    fn from_line(ln: usize, rows: Rows<'a>) -> Vec<Self> {
+        rows.into_iter()
+            .enumerate()
+            .map(|(row, graphemes)| Self {
+                ln: Some((ln, row)),
+                graphemes,
+            })
+            .collect()
+    }
+}
+
+/// Line the view is built around
 pub enum Anchor {
+    /// Line displayed on the first row
     Start(usize),
+    /// Line displayed in the middle of the view
     Center(usize),
+    /// Line displayed on the last rows
     End(usize),
+    /// Line displayed from a fixed row, blank rows fill the top
+    /// of the view if there are not enough lines above it
+    Row(usize, u16),
+    /// Text split in full screen pages, showing the page
+    /// containing the line
+    Page(usize),
+}
+
+/// Narrow column mapping the whole text onto the height of the view
+#[derive(Debug, Clone, Default)]
+pub struct Minimap {
+    track: Style,
+    progress: Option<(usize, Style)>,
+    marks: Vec<(usize, Style)>,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn track(mut self, style: Style) -> Self {
+        self.track = style;
+        self
+    }
+
+    /// Fill the track up to a line
+    pub fn progress(mut self, ln: usize, style: Style) -> Self {
+        self.progress = Some((ln, style));
+        self
+    }
+
+    /// Mark a line, later marks are drawn over earlier ones
+    pub fn mark(mut self, ln: usize, style: Style) -> Self {
+        self.marks.push((ln, style));
+        self
+    }
+
+    /// Rows of the minimap are spread over the text, one per line
+    /// when the text fits in the view
+    fn row_of(ln: usize, line_count: usize, height: u16) -> usize {
+        ln * height as usize / line_count.max(height as usize)
+    }
+
+    fn render(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        line_count: usize,
+        visible: RangeInclusive<usize>,
+    ) {
+        if line_count == 0 {
+            return;
+        }
+
+        let row_of = |ln| Self::row_of(ln, line_count, area.height);
+        let last_row = row_of(line_count.saturating_sub(1));
+        let visible = row_of(*visible.start())..=row_of(*visible.end());
+        let progress = self.progress.map(|(ln, style)| (row_of(ln), style));
+
+        for row in 0..=last_row.min(area.height as usize - 1) {
+            let (symbol, style) = if visible.contains(&row) {
+                ("┃", self.track)
+            } else {
+                ("│", self.track)
+            };
+            let style = match progress {
+                Some((progress, fill)) if row <= progress => style.patch(fill),
+                _ => style,
+            };
+
+            buf.get_mut(area.left(), area.top() + row as u16)
+                .set_symbol(symbol)
+                .set_style(style);
+        }
+
+        for &(ln, style) in &self.marks {
+            let row = row_of(ln);
+            if row < area.height as usize {
+                buf.get_mut(area.left(), area.top() + row as u16)
+                    .set_symbol("●")
+                    .set_style(style);
+            }
+        }
+    }
 }
 
-/// Lower level, stateless text displaying engine.
+/// Rows of the lines processed so far, kept across frames and
+/// cleared whenever the view width changes
+#[derive(Debug, Default)]
+pub struct TextViewState<'a> {
+    width: u16,
+    rows: HashMap<usize, Rows<'a>>,
+}
+
+impl<'a> TextViewState<'a> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn resize(&mut self, width: u16) {
+        if self.width != width {
+            self.width = width;
+            self.clear();
+        }
+    }
+}
+
+/// Lower level text displaying engine, processed rows can be kept
+/// across frames with a `TextViewState`
 pub struct TextView<'a, 'ln> {
     /// The full text buffer
     text_lines: &'ln [StyledLine<'a>],
@@ -35,19 +183,39 @@ pub struct TextView<'a, 'ln> {
 
     /// Option to override the background color after all styles are applied
     bg_color: Option<Color>,
+
+    /// Styles patched onto single graphemes, indexed by line then
+    /// grapheme, lines with overlays bypass the row cache
+    overlays: HashMap<usize, HashMap<usize, Style>>,
+
+    /// Graphemes inserted after single graphemes, indexed by line
+    /// then grapheme, lines with inlays bypass the row cache
+    inlays: HashMap<usize, HashMap<usize, Vec<StyledGrapheme<'ln>>>>,
+
+    /// Styles patched onto whole ranges of lines
+    line_patches: Vec<(Range<usize>, Style)>,
+
+    /// Style of the line numbers, no gutter is drawn if unset
+    gutter: Option<Style>,
+
+    minimap: Option<Minimap>,
 }
 
 impl<'a, 'ln> TextView<'a, 'ln> {
     /// Instantiate a TextView widget from a line buffer and use the builder
     /// pattern to set custom rendering options
-
     pub fn from_styled_content(lines: &'ln [StyledLine<'a>]) -> Self {
         Self {
             text_lines: lines,
-            line_processor: Box::new(LineStylizer),
+            line_processor: Box::new(LineStylizer::default()),
             anchor: Anchor::Start(0),
             block: Default::default(),
             bg_color: None,
+            overlays: Default::default(),
+            inlays: Default::default(),
+            line_patches: vec![],
+            gutter: None,
+            minimap: None,
         }
     }
 
@@ -65,14 +233,13 @@ impl<'a, 'ln> TextView<'a, 'ln> {
     }
 
     pub fn anchor(mut self, anchor: Anchor) -> Self {
+        let last_ln = self.text_lines.len().saturating_sub(1);
         self.anchor = match anchor {
-            Anchor::Start(anchor) if anchor >= self.text_lines.len() => {
-                panic!("Anchor out of bounds")
-            }
-            Anchor::End(anchor) => {
-                Anchor::End(min(anchor, self.text_lines.len()))
-            }
-            _ => anchor,
+            Anchor::Start(anchor) => Anchor::Start(min(anchor, last_ln)),
+            Anchor::Center(anchor) => Anchor::Center(min(anchor, last_ln)),
+            Anchor::End(anchor) => Anchor::End(min(anchor, last_ln)),
+            Anchor::Row(anchor, row) => Anchor::Row(min(anchor, last_ln), row),
+            Anchor::Page(anchor) => Anchor::Page(min(anchor, last_ln)),
         };
         self
     }
@@ -82,6 +249,82 @@ impl<'a, 'ln> TextView<'a, 'ln> {
         self
     }
 
+    /// Patch the style of single graphemes, applied at draw time
+    pub fn overlays(mut self, overlays: HashMap<TextCoord, Style>) -> Self {
+        for (coord, style) in overlays {
+            self.overlays
+                .entry(coord.ln)
+                .or_default()
+                .insert(coord.x, style);
+        }
+        self
+    }
+
+    /// Insert graphemes after the grapheme at `coord`, such as a
+    /// label next to a cursor, the rest of the line flows after them
+    pub fn inlay(
+        mut self,
+        coord: TextCoord,
+        graphemes: Vec<StyledGrapheme<'ln>>,
+    ) -> Self {
+        self.inlays
+            .entry(coord.ln)
+            .or_default()
+            .entry(coord.x)
+            .or_default()
+            .extend(graphemes);
+        self
+    }
+
+    /// Patch the style of every grapheme of a range of lines
+    pub fn line_patch(mut self, lines: Range<usize>, style: Style) -> Self {
+        self.line_patches.push((lines, style));
+        self
+    }
+
+    /// Display line numbers on the left of the text, wrapped rows
+    /// are left blank
+    pub fn gutter(mut self, style: Style) -> Self {
+        self.gutter = Some(style);
+        self
+    }
+
+    /// Display a minimap column on the right of the text
+    pub fn minimap(mut self, minimap: Minimap) -> Self {
+        self.minimap = Some(minimap);
+        self
+    }
+
+    fn gutter_width(&self) -> u16 {
+        match self.gutter {
+            Some(_) => self.text_lines.len().to_string().len() as u16 + 1,
+            None => 0,
+        }
+    }
+
+    fn render_gutter(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        rows: &[ViewRow<'ln>],
+    ) {
+        let style = self.gutter.unwrap_or_default();
+        buf.set_style(area, style);
+
+        let width = area.width as usize - 1;
+        for (y, row) in rows.iter().enumerate() {
+            if let Some((ln, 0)) = row.ln {
+                let number = format!("{:>width$}", ln + 1, width = width);
+                buf.set_string(
+                    area.left(),
+                    area.top() + y as u16,
+                    number,
+                    style,
+                );
+            }
+        }
+    }
+
     fn render_block(&mut self, area: &mut Rect, buf: &mut Buffer) {
         let block = std::mem::take(&mut self.block);
 
@@ -92,41 +335,119 @@ impl<'a, 'ln> TextView<'a, 'ln> {
         *area = inner_area;
     }
 
-    fn generate_view(&mut self, area: Rect) -> Vec<Vec<StyledGrapheme<'_>>> {
+    fn generate_view(
+        &self,
+        area: Rect,
+        cache: &mut TextViewState<'a>,
+    ) -> Vec<ViewRow<'ln>> {
+        if self.text_lines.is_empty() {
+            return vec![];
+        }
+
         match self.anchor {
-            Anchor::Center(anchor) => self.generate_center_anchor(anchor, area),
-            _ => panic!("Disabled anchors"),
+            Anchor::Start(anchor) => {
+                self.expand_rows_down(anchor, area, cache).1
+            }
+            Anchor::Center(anchor) => {
+                self.generate_center_anchor(anchor, area, cache)
+            }
+            Anchor::End(anchor) => self.expand_rows_up(anchor, area, cache).1,
+            Anchor::Row(anchor, row) => {
+                self.generate_row_anchor(anchor, row, area, cache)
+            }
+            Anchor::Page(anchor) => {
+                self.generate_page_anchor(anchor, area, cache)
+            }
         }
     }
 
     fn generate_center_anchor(
-        &mut self,
+        &self,
         anchor: usize,
         area: Rect,
-    ) -> Vec<Vec<StyledGrapheme<'_>>> {
+        cache: &mut TextViewState<'a>,
+    ) -> Vec<ViewRow<'ln>> {
         let half_height_area = Rect::new(0, 0, area.width, area.height / 2);
 
-        let (_, mut rows) = self.expand_rows_up(anchor, half_height_area);
+        let (_, mut rows) =
+            self.expand_rows_up(anchor, half_height_area, cache);
 
         let area = Rect::new(0, 0, area.width, area.height - rows.len() as u16); // cast should be safe
-        let (_, bottom_rows) = self.expand_rows_down(anchor + 1, area);
+        let (_, bottom_rows) = self.expand_rows_down(anchor + 1, area, cache);
         rows.extend(bottom_rows);
 
         rows
     }
 
+    fn generate_row_anchor(
+        &self,
+        anchor: usize,
+        row: u16,
+        area: Rect,
+        cache: &mut TextViewState<'a>,
+    ) -> Vec<ViewRow<'ln>> {
+        let row = min(row, area.height.saturating_sub(1));
+
+        let mut rows = if anchor > 0 && row > 0 {
+            let top_area = Rect::new(0, 0, area.width, row);
+            self.expand_rows_up(anchor - 1, top_area, cache).1
+        } else {
+            vec![]
+        };
+        let padding = row as usize - rows.len();
+        rows.splice(
+            0..0,
+            std::iter::repeat_with(ViewRow::padding).take(padding),
+        );
+
+        let bottom_area = Rect::new(0, 0, area.width, area.height - row);
+        let (_, bottom_rows) =
+            self.expand_rows_down(anchor, bottom_area, cache);
+        rows.extend(bottom_rows);
+
+        rows
+    }
+
+    /// Page boundaries depend on the row counts of every line up to the
+    /// anchor, these lines get wrapped and styled once then the counts
+    /// are read from the cache on the following frames
+    fn generate_page_anchor(
+        &self,
+        anchor: usize,
+        area: Rect,
+        cache: &mut TextViewState<'a>,
+    ) -> Vec<ViewRow<'ln>> {
+        let height = area.height as usize;
+        let mut page_start = 0;
+        let mut page_rows = 0;
+
+        for ln in 0..=anchor {
+            let line_rows = self.cached_rows(ln, area.width, cache).len();
+
+            // a line taller than the view gets a page of its own
+            if page_rows + line_rows > height && ln > page_start {
+                page_start = ln;
+                page_rows = 0;
+            }
+            page_rows += line_rows;
+        }
+
+        self.expand_rows_down(page_start, area, cache).1
+    }
+
     /// Generates rows downwards and returns the line nb past the last rendered line along with the rows
     fn expand_rows_down(
         &self,
         start_ln: usize,
         area: Rect,
-    ) -> (usize, Vec<Vec<StyledGrapheme<'_>>>) {
+        cache: &mut TextViewState<'a>,
+    ) -> (usize, Vec<ViewRow<'ln>>) {
         let total_lines = self.text_lines.len();
-        let mut rows: Vec<Vec<StyledGrapheme<'_>>> = vec![];
+        let mut rows = vec![];
 
         for current_ln in start_ln..total_lines {
-            let line_as_rows = self.line_to_rows(current_ln, area.width);
-            rows.extend(line_as_rows);
+            let line_as_rows = self.line_to_rows(current_ln, area.width, cache);
+            rows.extend(ViewRow::from_line(current_ln, line_as_rows));
             if rows.len() > area.height.into() {
                 rows.truncate(area.height.into());
                 return (current_ln, rows);
@@ -141,14 +462,13 @@ impl<'a, 'ln> TextView<'a, 'ln> {
         &self,
         start_ln: usize,
         area: Rect,
-    ) -> (usize, Vec<Vec<StyledGrapheme<'_>>>) {
-        let mut rows: Vec<Vec<StyledGrapheme<'_>>> = vec![];
+        cache: &mut TextViewState<'a>,
+    ) -> (usize, Vec<ViewRow<'ln>>) {
+        let mut rows = vec![];
 
         for current_ln in (0..=start_ln).rev() {
-            let mut line_as_rows = self.line_to_rows(current_ln, area.width);
-            // if line_as_rows.len() + rows.len() > area.height as usize {
-            //     return (current_ln + 1, rows);
-            // }
+            let line_as_rows = self.line_to_rows(current_ln, area.width, cache);
+            let mut line_as_rows = ViewRow::from_line(current_ln, line_as_rows);
             line_as_rows.extend(rows);
             rows = line_as_rows;
 
@@ -165,21 +485,84 @@ impl<'a, 'ln> TextView<'a, 'ln> {
         (0, rows)
     }
 
+    /// Rows of a line with its overlays and line patches applied
     fn line_to_rows(
         &self,
         line_nb: usize,
         width: u16,
-    ) -> Vec<Vec<StyledGrapheme<'_>>> {
-        let line = self.text_lines[line_nb].as_slice();
-        let mut graphemes = line.to_owned().into_iter();
-        let bg = self.bg_color.unwrap_or(Color::Reset);
+        cache: &mut TextViewState<'a>,
+    ) -> Rows<'ln> {
+        let overlays = self.overlays.get(&line_nb);
+        let inlays = self.inlays.get(&line_nb);
+
+        let mut rows = match (overlays, inlays) {
+            (None, None) => self.cached_rows(line_nb, width, cache).clone(),
+            _ => {
+                let line = self.text_lines[line_nb].iter().enumerate();
+                let mut graphemes = line.flat_map(|(x, g)| {
+                    let style = overlays
+                        .and_then(|overlays| overlays.get(&x))
+                        .map_or(g.style, |overlay| g.style.patch(*overlay));
+                    let inlay = inlays.and_then(|inlays| inlays.get(&x));
+
+                    iter::once(StyledGrapheme {
+                        symbol: g.symbol,
+                        style,
+                    })
+                    .chain(inlay.into_iter().flatten().cloned())
+                });
+                self.process_line(&mut graphemes, width)
+            }
+        };
+
+        let patch = self
+            .line_patches
+            .iter()
+            .filter(|(lines, _)| lines.contains(&line_nb))
+            .fold(None, |acc: Option<Style>, (_, style)| {
+                Some(acc.unwrap_or_default().patch(*style))
+            });
+
+        if let Some(patch) = patch {
+            for g in rows.iter_mut().flatten() {
+                g.style = g.style.patch(patch);
+            }
+        }
+
+        rows
+    }
+
+    fn cached_rows<'c>(
+        &self,
+        line_nb: usize,
+        width: u16,
+        cache: &'c mut TextViewState<'a>,
+    ) -> &'c Rows<'a> {
+        cache.rows.entry(line_nb).or_insert_with(|| {
+            let mut graphemes = self.text_lines[line_nb].iter().cloned();
+            self.process_line(&mut graphemes, width)
+        })
+    }
 
-        self.line_processor.process_line(&mut graphemes, width, bg)
+    fn process_line<'g>(
+        &self,
+        graphemes: &mut dyn Iterator<Item = StyledGrapheme<'g>>,
+        width: u16,
+    ) -> Rows<'g> {
+        let bg = self.bg_color.unwrap_or(Color::Reset);
+        self.line_processor.process_line(graphemes, width, bg)
     }
 }
 
-impl<'a, 'ln> Widget for TextView<'a, 'ln> {
-    fn render(mut self, mut area: Rect, buf: &mut Buffer) {
+impl<'a, 'ln> StatefulWidget for TextView<'a, 'ln> {
+    type State = TextViewState<'a>;
+
+    fn render(
+        mut self,
+        mut area: Rect,
+        buf: &mut Buffer,
+        state: &mut Self::State,
+    ) {
         self.render_block(&mut area, buf);
         if area.height < 1 || area.width < 1 {
             return;
@@ -189,12 +572,58 @@ impl<'a, 'ln> Widget for TextView<'a, 'ln> {
             Style::default().bg(self.bg_color.unwrap_or(Color::Reset));
         buf.set_style(area, bg_style);
 
-        let lines = self.generate_view(area);
+        // side columns are dropped when there is no room left for text
+        let gutter_width = self.gutter_width();
+        let minimap_width = if self.minimap.is_some() { 2 } else { 0 };
+        let side_width = gutter_width + minimap_width;
+        let (gutter_width, minimap_width) = if side_width < area.width {
+            (gutter_width, minimap_width)
+        } else {
+            (0, 0)
+        };
+
+        let text_area = Rect {
+            x: area.x + gutter_width,
+            width: area.width - gutter_width - minimap_width,
+            ..area
+        };
+
+        state.resize(text_area.width);
+        let rows = self.generate_view(text_area, state);
+
+        if gutter_width > 0 {
+            let gutter_area = Rect {
+                width: gutter_width,
+                ..area
+            };
+            self.render_gutter(gutter_area, buf, &rows);
+        }
+        if let (Some(minimap), true) = (&self.minimap, minimap_width > 0) {
+            let mut lines = rows.iter().filter_map(|row| row.ln);
+            let first = lines.clone().next().map_or(0, |(ln, _)| ln);
+            let last = lines.next_back().map_or(first, |(ln, _)| ln);
+            let minimap_area = Rect {
+                x: area.right() - 1,
+                width: 1,
+                ..area
+            };
+            minimap.render(
+                minimap_area,
+                buf,
+                self.text_lines.len(),
+                first..=last,
+            );
+        }
+
+        let area = text_area;
         let mut y = 0;
-        for line in lines {
+        for row in rows {
             let mut x = 0;
-            for StyledGrapheme { symbol, style } in line {
-                if area.left() + x < buf.area.width {
+            for StyledGrapheme { symbol, style } in row.graphemes {
+                let width = symbol.width() as u16;
+
+                // wide graphemes are only drawn if they fit entirely
+                if x + width.max(1) <= area.width {
                     buf.get_mut(area.left() + x, area.top() + y)
                         .set_symbol(if symbol.is_empty() {
                             " "
@@ -203,7 +632,7 @@ impl<'a, 'ln> Widget for TextView<'a, 'ln> {
                         })
                         .set_style(style);
                 }
-                x += symbol.width() as u16;
+                x += width;
             }
             y += 1;
             if y >= area.height {
@@ -212,3 +641,118 @@ impl<'a, 'ln> Widget for TextView<'a, 'ln> {
         }
     }
 }
+
+impl<'a, 'ln> Widget for TextView<'a, 'ln> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        StatefulWidget::render(self, area, buf, &mut TextViewState::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highlighting::{Highlighter, NoOpHighlighter};
+
+    fn view_rows(text: &str, anchor: Anchor, height: u16) -> Vec<String> {
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let styled = NoOpHighlighter.highlight(&lines);
+        let view = TextView::from_styled_content(&styled).anchor(anchor);
+        render_rows(view, height)
+    }
+
+    fn render_rows(view: TextView, height: u16) -> Vec<String> {
+        let area = Rect::new(0, 0, 10, height);
+        let mut buf = Buffer::empty(area);
+        Widget::render(view, area, &mut buf);
+
+        (0..height)
+            .map(|y| {
+                (0..area.width)
+                    .map(|x| buf.get(x, y).symbol.clone())
+                    .collect::<String>()
+                    .trim_end()
+                    .trim_end_matches('⏎')
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn anchors() {
+        let text = "0\n1\n2\n3\n4\n5\n6\n";
+        let first = |rows: Vec<String>| rows[0].clone();
+
+        assert_eq!(first(view_rows(text, Anchor::Start(2), 3)), "2");
+        assert_eq!(first(view_rows(text, Anchor::End(4), 3)), "2");
+        assert_eq!(first(view_rows(text, Anchor::Center(4), 4)), "3");
+        assert_eq!(view_rows(text, Anchor::Row(0, 1), 3), ["", "0", "1"]);
+        assert_eq!(first(view_rows(text, Anchor::Row(5, 1), 3)), "4");
+        assert_eq!(first(view_rows(text, Anchor::Page(2), 3)), "0");
+        assert_eq!(first(view_rows(text, Anchor::Page(3), 3)), "3");
+        assert_eq!(first(view_rows(text, Anchor::Page(6), 3)), "6");
+
+        // anchors past the end stick to the last line
+        assert_eq!(first(view_rows(text, Anchor::Start(99), 3)), "6");
+        assert_eq!(first(view_rows(text, Anchor::Center(99), 4)), "5");
+        assert_eq!(first(view_rows(text, Anchor::Row(99, 1), 3)), "5");
+        assert_eq!(first(view_rows(text, Anchor::Page(99), 3)), "6");
+    }
+
+    #[test]
+    fn visible_rows_only() {
+        let text = "line\n".repeat(1000);
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let styled = NoOpHighlighter.highlight(&lines);
+        let mut state = TextViewState::new();
+
+        let area = Rect::new(0, 0, 10, 3);
+        let mut buf = Buffer::empty(area);
+        let view =
+            TextView::from_styled_content(&styled).anchor(Anchor::Center(500));
+        StatefulWidget::render(view, area, &mut buf, &mut state);
+        assert!(state.rows.len() <= 4);
+
+        let area = Rect::new(0, 0, 5, 3);
+        let mut buf = Buffer::empty(area);
+        let view =
+            TextView::from_styled_content(&styled).anchor(Anchor::Page(999));
+        StatefulWidget::render(view, area, &mut buf, &mut state);
+        assert_eq!(state.width, 5);
+        assert_eq!(buf.get(0, 0).symbol, "l");
+    }
+
+    #[test]
+    fn gutter_and_minimap() {
+        let text = "0123456789ab\nx\n";
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let styled = NoOpHighlighter.highlight(&lines);
+
+        let view = TextView::from_styled_content(&styled)
+            .gutter(Style::default())
+            .minimap(Minimap::new().mark(1, Style::default()));
+        assert_eq!(
+            render_rows(view, 4),
+            ["1 012345 ┃", "  6789ab ●", "  ", "2 x"]
+        );
+
+        let view =
+            TextView::from_styled_content(&styled).gutter(Style::default());
+        assert_eq!(render_rows(view, 3), ["1 01234567", "  89ab", "2 x"]);
+    }
+
+    #[test]
+    fn inlays() {
+        let lines = ["abc"];
+        let styled = NoOpHighlighter.highlight(&lines);
+        let tag = |symbol| StyledGrapheme {
+            symbol,
+            style: Style::default(),
+        };
+
+        let view = TextView::from_styled_content(&styled)
+            .inlay(TextCoord::new(0, 0), vec![tag("x")])
+            .inlay(TextCoord::new(0, 0), vec![tag("y")])
+            .inlay(TextCoord::new(0, 2), vec![tag("z")]);
+        assert_eq!(render_rows(view, 1), ["axybcz"]);
+    }
+}
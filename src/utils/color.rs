@@ -0,0 +1,174 @@
+use once_cell::sync::OnceCell;
+use std::env;
+use tui::style::Color;
+
+/// Color capabilities of the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    NoColor,
+}
+
+/// The 16 ANSI colors with the RGB values of the xterm defaults
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorSupport {
+    /// Color support of the current terminal, detected once
+    pub fn current() -> Self {
+        static SUPPORT: OnceCell<ColorSupport> = OnceCell::new();
+        *SUPPORT.get_or_init(|| {
+            Self::from_env(
+                env::var("NO_COLOR").ok().as_deref(),
+                env::var("COLORTERM").ok().as_deref(),
+                env::var("TERM").ok().as_deref(),
+            )
+        })
+    }
+
+    /// Detection based on the `NO_COLOR`, `COLORTERM` and `TERM`
+    /// environment variables
+    pub fn from_env(
+        no_color: Option<&str>,
+        colorterm: Option<&str>,
+        term: Option<&str>,
+    ) -> Self {
+        if no_color.is_some_and(|v| !v.is_empty()) {
+            return ColorSupport::NoColor;
+        }
+
+        let colorterm = colorterm.unwrap_or_default().to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorSupport::TrueColor;
+        }
+
+        match term.unwrap_or_default() {
+            "dumb" => ColorSupport::NoColor,
+            t if t.contains("direct") => ColorSupport::TrueColor,
+            t if t.contains("256color") => ColorSupport::Ansi256,
+            _ => ColorSupport::Ansi16,
+        }
+    }
+
+    /// Closest color this terminal can display, colors are
+    /// dropped altogether without color support
+    pub fn degrade(self, color: Color) -> Color {
+        match (self, color) {
+            (_, Color::Reset) | (ColorSupport::TrueColor, _) => color,
+            (ColorSupport::NoColor, _) => Color::Reset,
+            (ColorSupport::Ansi256, Color::Rgb(r, g, b)) => {
+                Color::Indexed(rgb_to_ansi256(r, g, b))
+            }
+            (ColorSupport::Ansi256, _) => color,
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => {
+                rgb_to_ansi16(r, g, b)
+            }
+            (ColorSupport::Ansi16, Color::Indexed(i)) => {
+                let (r, g, b) = ansi256_to_rgb(i);
+                rgb_to_ansi16(r, g, b)
+            }
+            (ColorSupport::Ansi16, _) => color,
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// Closest entry of the xterm 6x6x6 color cube or grayscale ramp
+pub fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (i32::from(CUBE_LEVELS[i]) - i32::from(v)).abs())
+            .unwrap()
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (16 + 36 * ri + 6 * gi + bi) as u8;
+    let cube_rgb = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    let avg = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+    let gray_index = (avg.saturating_sub(8) / 10).min(23) as u8;
+    let gray = 8 + gray_index * 10;
+
+    if distance((gray, gray, gray), (r, g, b)) < distance(cube_rgb, (r, g, b)) {
+        232 + gray_index
+    } else {
+        cube
+    }
+}
+
+pub fn ansi256_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            let level = |v: u8| CUBE_LEVELS[v as usize];
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// Closest of the 16 ANSI colors
+pub fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection() {
+        use ColorSupport::*;
+
+        let detect = ColorSupport::from_env;
+        assert_eq!(detect(Some("1"), Some("truecolor"), None), NoColor);
+        assert_eq!(detect(Some(""), Some("truecolor"), None), TrueColor);
+        assert_eq!(detect(None, None, Some("screen-256color")), Ansi256);
+        assert_eq!(detect(None, None, Some("xterm")), Ansi16);
+        assert_eq!(detect(None, None, Some("dumb")), NoColor);
+    }
+
+    #[test]
+    fn degradation() {
+        assert_eq!(rgb_to_ansi256(0, 0, 0), 16);
+        assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
+        assert_eq!(rgb_to_ansi256(20, 20, 20), 233);
+        assert_eq!(ansi256_to_rgb(196), (255, 0, 0));
+
+        let ansi16 = ColorSupport::Ansi16;
+        assert_eq!(ansi16.degrade(Color::Rgb(250, 10, 10)), Color::LightRed);
+        assert_eq!(ansi16.degrade(Color::Indexed(233)), Color::Black);
+        assert_eq!(ColorSupport::NoColor.degrade(Color::Red), Color::Reset);
+    }
+}
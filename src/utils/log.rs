@@ -3,7 +3,6 @@ use std::{fs, io::Write};
 pub fn log(s: &str) {
     let mut file = fs::OpenOptions::new()
         .create(true)
-        .write(true)
         .append(true)
         .open("log.txt")
         .unwrap();
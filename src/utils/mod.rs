@@ -1,3 +1,4 @@
+pub mod color;
 pub mod helpers;
 pub mod log;
 pub mod syntect;
@@ -1,15 +1,139 @@
+use crate::config::Config;
 use once_cell::sync::OnceCell;
-use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
+use std::{fs, path::Path};
+use syntect::{
+    highlighting::{Theme, ThemeSet},
+    parsing::{SyntaxDefinition, SyntaxReference, SyntaxSet},
+};
+
+use super::types::AsyncResult;
 
 pub fn syntect_load_defaults() -> (&'static SyntaxSet, &'static ThemeSet) {
     static SYNTAX_SET: OnceCell<SyntaxSet> = OnceCell::new();
     static THEME_SET: OnceCell<ThemeSet> = OnceCell::new();
     (
-        SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines),
-        THEME_SET.get_or_init(ThemeSet::load_defaults),
+        SYNTAX_SET.get_or_init(|| {
+            let syntax_set = SyntaxSet::load_defaults_newlines();
+            load_user_syntaxes(syntax_set)
+        }),
+        THEME_SET.get_or_init(|| {
+            let mut theme_set = ThemeSet::load_defaults();
+            load_user_themes(&mut theme_set);
+            theme_set
+        }),
     )
 }
 
+/// Add the `.sublime-syntax` files found in `<config dir>/dacttylo/syntaxes`
+/// to the syntax set, files that fail to load are skipped
+fn load_user_syntaxes(syntax_set: SyntaxSet) -> SyntaxSet {
+    let entries = Config::dir()
+        .map(|dir| dir.join("syntaxes"))
+        .and_then(|dir| fs::read_dir(dir).ok());
+
+    let definitions = entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "sublime-syntax")
+        })
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            let name = path.file_stem().and_then(|s| s.to_str());
+            SyntaxDefinition::load_from_str(&content, true, name).ok()
+        })
+        .collect::<Vec<_>>();
+
+    if definitions.is_empty() {
+        return syntax_set;
+    }
+
+    let mut builder = syntax_set.into_builder();
+    for definition in definitions {
+        builder.add(definition);
+    }
+    builder.build()
+}
+
+/// Add the `.tmTheme` files found in `<config dir>/dacttylo/themes`
+/// to the theme set, files that fail to load are skipped
+fn load_user_themes(theme_set: &mut ThemeSet) {
+    let paths = Config::dir()
+        .map(|dir| dir.join("themes"))
+        .filter(|dir| dir.is_dir())
+        .and_then(|dir| ThemeSet::discover_theme_paths(dir).ok())
+        .unwrap_or_default();
+
+    for path in paths {
+        let name = path.file_stem().and_then(|s| s.to_str());
+        if let (Some(name), Ok(theme)) = (name, ThemeSet::get_theme(&path)) {
+            theme_set.themes.insert(name.to_owned(), theme);
+        }
+    }
+}
+
+/// Names of the bundled and user themes, sorted alphabetically
+pub fn theme_names() -> Vec<&'static str> {
+    let (_, theme_set) = syntect_load_defaults();
+    let mut names: Vec<&str> =
+        theme_set.themes.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    names
+}
+
+pub fn find_theme(name: &str) -> AsyncResult<&'static Theme> {
+    let (_, theme_set) = syntect_load_defaults();
+    theme_set.themes.get(name).ok_or_else(|| {
+        format!(
+            "unknown theme `{}`, run `dacttylo themes list` to see the available themes",
+            name
+        )
+        .into()
+    })
+}
+
+/// Find a syntax by name (`Rust`) or by file extension (`rs`)
+pub fn find_syntax(name: &str) -> AsyncResult<&'static SyntaxReference> {
+    let (syntax_set, _) = syntect_load_defaults();
+    syntax_set
+        .find_syntax_by_name(name)
+        .or_else(|| syntax_set.find_syntax_by_token(name))
+        .ok_or_else(|| format!("unknown syntax `{}`", name).into())
+}
+
+/// Syntax used to highlight a file: the `name` override if any, otherwise
+/// detection from the file extension, then from the first line of `text`,
+/// then plain text
+pub fn resolve_syntax(
+    file: impl AsRef<Path>,
+    text: &str,
+    name: Option<&str>,
+) -> AsyncResult<&'static SyntaxReference> {
+    if let Some(name) = name {
+        return find_syntax(name);
+    }
+
+    let (syntax_set, _) = syntect_load_defaults();
+    let path = file.as_ref();
+    let by_file = [path.file_name(), path.extension()]
+        .into_iter()
+        .flatten()
+        .filter_map(|token| token.to_str())
+        .find_map(|token| syntax_set.find_syntax_by_extension(token));
+
+    Ok(by_file
+        .or_else(|| syntax_set.find_syntax_by_first_line(text))
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text()))
+}
+
+/// Like `find_syntax`, with a plain text fallback for syntaxes
+/// that are not available locally
+pub fn find_syntax_or_plain(name: &str) -> &'static SyntaxReference {
+    let (syntax_set, _) = syntect_load_defaults();
+    find_syntax(name).unwrap_or_else(|_| syntax_set.find_syntax_plain_text())
+}
+
 pub fn syntect_to_tui_style(
     syntect_style: syntect::highlighting::Style,
 ) -> tui::style::Style {
@@ -38,3 +162,35 @@ pub fn syntect_to_tui_style(
 
     style
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syntax_resolution_fallbacks() {
+        let name = |file, text, name| {
+            resolve_syntax(file, text, name).unwrap().name.as_str()
+        };
+
+        assert_eq!(name("main.rs", "", None), "Rust");
+        assert_eq!(name("main.rs", "", Some("py")), "Python");
+        assert_eq!(
+            name("script", "#!/bin/bash\n", None),
+            "Bourne Again Shell (bash)"
+        );
+        assert_eq!(name("build.zig", "const std = 0;\n", None), "Plain Text");
+        assert!(resolve_syntax("main.rs", "", Some("nope")).is_err());
+    }
+
+    #[test]
+    fn theme_lookup() {
+        let names = theme_names();
+        assert!(names.windows(2).all(|w| w[0] <= w[1]));
+        assert!(names.contains(&"base16-ocean.dark"));
+        assert!(find_theme("base16-ocean.dark").is_ok());
+
+        let err = find_theme("nope").unwrap_err().to_string();
+        assert!(err.starts_with("unknown theme `nope`"));
+    }
+}
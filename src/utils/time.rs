@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
 
 pub fn datetime_in(delay: chrono::Duration) -> Option<DateTime<Utc>> {
     let future_date = Utc::now().checked_add_signed(delay)?;
@@ -14,3 +15,120 @@ pub async fn wake_up(at: Option<DateTime<Utc>>) -> Option<()> {
 
     Some(())
 }
+
+/// Converts a wall-clock date to a monotonic instant, dates too far
+/// in the past to be represented map to the current instant
+pub fn instant_at(at: DateTime<Utc>) -> Instant {
+    let now = Instant::now();
+    let delay = at.signed_duration_since(Utc::now());
+
+    match delay.to_std() {
+        Ok(delay) => now + delay,
+        Err(_) => (-delay)
+            .to_std()
+            .ok()
+            .and_then(|ago| now.checked_sub(ago))
+            .unwrap_or(now),
+    }
+}
+
+/// Monotonic clock that can be paused, the time spent paused
+/// is excluded from the elapsed duration
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    start: Option<Instant>,
+    paused_at: Option<Instant>,
+    paused_total: Duration,
+}
+
+impl Stopwatch {
+    /// A stopwatch that is already running
+    pub fn new() -> Self {
+        let mut watch = Self::idle();
+        watch.start();
+        watch
+    }
+
+    /// A stopwatch that stays at zero until `start` is called
+    pub fn idle() -> Self {
+        Self {
+            start: None,
+            paused_at: None,
+            paused_total: Duration::ZERO,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.start_at(Instant::now());
+    }
+
+    /// Start counting from `origin`, which may be in the past
+    pub fn start_at(&mut self, origin: Instant) {
+        if self.start.is_none() {
+            self.start = Some(origin);
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.start.is_some()
+    }
+
+    pub fn pause(&mut self) {
+        if self.is_started() && self.paused_at.is_none() {
+            self.paused_at = Some(Instant::now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_total += Instant::now().duration_since(paused_at);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match self.start {
+            Some(start) => {
+                let now = self.paused_at.unwrap_or_else(Instant::now);
+                now.duration_since(start).saturating_sub(self.paused_total)
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_time_is_excluded() {
+        let mut watch = Stopwatch::new();
+        std::thread::sleep(Duration::from_millis(20));
+
+        watch.pause();
+        let frozen = watch.elapsed();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(watch.elapsed(), frozen);
+
+        watch.resume();
+        assert!(watch.elapsed() < frozen + Duration::from_millis(20));
+        assert!(!watch.is_paused());
+    }
+
+    #[test]
+    fn idle_until_started() {
+        let mut watch = Stopwatch::idle();
+        watch.pause();
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(watch.elapsed(), Duration::ZERO);
+        assert!(!watch.is_paused());
+
+        watch.start();
+        assert!(watch.is_started());
+    }
+}
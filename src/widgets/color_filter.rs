@@ -0,0 +1,30 @@
+use crate::utils::color::ColorSupport;
+use tui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+/// Rewrites the colors already drawn in an area to the closest ones
+/// the terminal supports, meant to be rendered last
+pub struct ColorFilter {
+    support: ColorSupport,
+}
+
+impl ColorFilter {
+    pub fn new(support: ColorSupport) -> Self {
+        Self { support }
+    }
+}
+
+impl Widget for ColorFilter {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if self.support == ColorSupport::TrueColor {
+            return;
+        }
+
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                cell.fg = self.support.degrade(cell.fg);
+                cell.bg = self.support.degrade(cell.bg);
+            }
+        }
+    }
+}
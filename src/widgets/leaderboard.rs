@@ -0,0 +1,155 @@
+use crate::app::state::PlayerStatus;
+use std::time::Duration;
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Widget},
+};
+
+/// Live standing of a player in the race
+#[derive(Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub status: PlayerStatus,
+
+    /// Share of the text typed, between 0 and 1
+    pub progress: f64,
+    pub wpm: f64,
+    pub mistakes: usize,
+
+    /// Time of the last input, which is the completion time
+    /// of finished players
+    pub elapsed: Duration,
+
+    /// Whether this entry is the local player
+    pub is_main: bool,
+}
+
+/// Sidebar listing players sorted by position, each on a line with
+/// its stats followed by a progress bar
+pub struct LeaderboardWidget<'b> {
+    entries: Vec<LeaderboardEntry>,
+    block: Block<'b>,
+}
+
+impl<'b> LeaderboardWidget<'b> {
+    pub fn new(mut entries: Vec<LeaderboardEntry>) -> Self {
+        entries.sort_by(|a, b| {
+            a.status.cmp(&b.status).then_with(|| match a.status {
+                PlayerStatus::Finished => a.elapsed.cmp(&b.elapsed),
+                _ => b.progress.total_cmp(&a.progress),
+            })
+        });
+
+        Self {
+            entries,
+            block: Default::default(),
+        }
+    }
+
+    pub fn block(mut self, block: Block<'b>) -> Self {
+        self.block = block;
+        self
+    }
+
+    fn render_entry(
+        spot: usize,
+        entry: &LeaderboardEntry,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        let mut style = Style::default().fg(Color::White);
+        if entry.is_main {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        let status = match entry.status {
+            PlayerStatus::Finished => "done".to_owned(),
+            PlayerStatus::Forfeited => "forfeit".to_owned(),
+            PlayerStatus::Typing => {
+                format!("{:.0}%", entry.progress * 100.0)
+            }
+        };
+        let name = format!("{}. {}", spot + 1, entry.name);
+        let (x, y) = (area.left(), area.top());
+        buf.set_stringn(x, y, &name, area.width as usize, style);
+        let status_x = area.right().saturating_sub(status.len() as u16);
+        buf.set_string(status_x, y, &status, style);
+
+        if area.height < 2 {
+            return;
+        }
+        let stats = format!("{:.0} wpm  {} err", entry.wpm, entry.mistakes);
+        let stats_style = Style::default().fg(Color::DarkGray);
+        buf.set_stringn(x, y + 1, &stats, area.width as usize, stats_style);
+
+        if area.height < 3 {
+            return;
+        }
+        let bar_style = match entry.status {
+            PlayerStatus::Forfeited => Style::default().fg(Color::DarkGray),
+            _ => Style::default().fg(Color::Green),
+        };
+        let filled = (entry.progress * area.width as f64).round() as usize;
+        let filled = filled.min(area.width as usize);
+        let empty = area.width as usize - filled;
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(empty));
+        buf.set_string(x, y + 2, &bar, bar_style);
+    }
+}
+
+impl<'b> Widget for LeaderboardWidget<'b> {
+    fn render(mut self, area: Rect, buf: &mut Buffer) {
+        let block = std::mem::take(&mut self.block);
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        // every entry takes 3 rows plus a blank separator
+        let rows = (inner_area.top()..inner_area.bottom()).step_by(4);
+        for (spot, (entry, y)) in self.entries.iter().zip(rows).enumerate() {
+            let height = (inner_area.bottom() - y).min(3);
+            let area = Rect {
+                y,
+                height,
+                ..inner_area
+            };
+            Self::render_entry(spot, entry, area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        name: &str,
+        status: PlayerStatus,
+        progress: f64,
+    ) -> LeaderboardEntry {
+        LeaderboardEntry {
+            name: name.to_owned(),
+            status,
+            progress,
+            wpm: 0.0,
+            mistakes: 0,
+            elapsed: Duration::from_secs((progress * 10.0) as u64),
+            is_main: false,
+        }
+    }
+
+    #[test]
+    fn sorted_by_position() {
+        let widget = LeaderboardWidget::new(vec![
+            entry("quitter", PlayerStatus::Forfeited, 0.9),
+            entry("slow", PlayerStatus::Typing, 0.2),
+            entry("fast", PlayerStatus::Typing, 0.6),
+            entry("second", PlayerStatus::Finished, 1.0),
+            entry("first", PlayerStatus::Finished, 0.5),
+        ]);
+
+        let names: Vec<&str> =
+            widget.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["first", "second", "fast", "slow", "quitter"]);
+    }
+}
@@ -1,2 +1,4 @@
+pub mod color_filter;
 pub mod figtext;
+pub mod leaderboard;
 pub mod wpm;